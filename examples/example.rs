@@ -1,7 +1,5 @@
 use std::borrow::Cow;
 
-use tokio_stream::StreamExt;
-
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let client = testground::client::Client::new_and_init().await?;
@@ -33,38 +31,31 @@ async fn publish_subscribe(
 ) -> Result<(), Box<dyn std::error::Error>> {
     client.record_message("running the publish_subscribe test");
 
-    match client.global_seq() {
-        1 => {
-            client.record_message("I am instance 1: acting as the leader");
+    if client.is_global_leader() {
+        client.record_message("I am instance 1: acting as the leader");
 
-            let json = serde_json::json!({"foo": "bar"});
-            client.publish("demonstration", Cow::Owned(json)).await?;
-            client.record_success().await?;
-        }
-        _ => {
-            client.record_message(format!(
-                "I am instance {}: acting as a follower",
-                client.global_seq()
-            ));
+        let json = serde_json::json!({"foo": "bar"});
+        client.publish("demonstration", Cow::Owned(json)).await?;
+        client.record_success().await?;
+    } else {
+        client.record_message(format!(
+            "I am instance {}: acting as a follower",
+            client.global_seq()
+        ));
 
-            let payload = client
-                .subscribe("demonstration", u16::MAX.into())
-                .await
-                .take(1)
-                .map(|x| x.unwrap())
-                .next()
-                .await
-                .unwrap();
+        let payload = client
+            .subscribe_collect("demonstration", 1)
+            .await?
+            .remove(0);
 
-            client.record_message(format!("I received the payload: {}", payload));
+        client.record_message(format!("I received the payload: {}", payload));
 
-            if payload["foo"].as_str() == Some("bar") {
-                client.record_success().await?;
-            } else {
-                client
-                    .record_failure(format!("invalid payload: {}", payload))
-                    .await?;
-            }
+        if payload["foo"].as_str() == Some("bar") {
+            client.record_success().await?;
+        } else {
+            client
+                .record_failure(format!("invalid payload: {}", payload))
+                .await?;
         }
     }
     Ok(())
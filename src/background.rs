@@ -1,22 +1,77 @@
 use std::collections::HashMap;
-
-use futures::stream::StreamExt;
-use influxdb::{Client, WriteQuery};
-use soketto::handshake::ServerResponse;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::{stream::StreamExt, FutureExt};
+#[cfg(feature = "metrics")]
+use influxdb::{Client, Query, WriteQuery};
+use soketto::{handshake::ServerResponse, Incoming};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::sync::{mpsc, oneshot};
 use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
 
 use crate::events::LogLine;
 use crate::{
-    errors::Error,
+    client::ReconnectPolicy,
+    context,
+    errors::{Error, InitError},
     events::{Event, EventType},
+    local::LocalSyncService,
     network_conf::NetworkConfiguration,
-    params::RunParameters,
+    params::{RunParameters, RunnerKind},
     requests::{PayloadType, Request, RequestType},
     responses::{RawResponse, Response, ResponseType},
 };
 
-const WEBSOCKET_RECEIVER: &str = "Websocket Receiver";
+/// The transport underlying the websocket connection to the sync service.
+pub trait Socket: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Socket for T {}
+
+/// A socket counting the bytes written to it, telling whether a failed
+/// request was written partially, see [`BackgroundTask::send`].
+struct Counted {
+    inner: Box<dyn Socket>,
+    written: Arc<AtomicU64>,
+}
+
+impl AsyncRead for Counted {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Counted {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let res = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = res {
+            self.written.fetch_add(written as u64, Ordering::Relaxed);
+        }
+        res
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
 
 #[derive(Debug)]
 pub enum Command {
@@ -28,6 +83,20 @@ pub enum Command {
     Subscribe {
         topic: String,
         stream: mpsc::Sender<Result<serde_json::Value, Error>>,
+        /// Cancels the subscription once sent, see [`Subscription`](crate::subscription::Subscription).
+        cancel: Option<oneshot::Receiver<()>>,
+    },
+    SubscribeEvents {
+        stream: mpsc::Sender<Result<serde_json::Value, Error>>,
+    },
+    GroupPublish {
+        topic: String,
+        message: serde_json::Value,
+        sender: oneshot::Sender<Result<u64, Error>>,
+    },
+    GroupSubscribe {
+        topic: String,
+        stream: mpsc::Sender<Result<serde_json::Value, Error>>,
     },
 
     SignalEntry {
@@ -41,7 +110,24 @@ pub enum Command {
         sender: oneshot::Sender<Result<(), Error>>,
     },
 
-    WaitNetworkInitializedStart {
+    /// See [`Client::state_count`](crate::client::Client::state_count).
+    #[cfg(any(test, feature = "testing"))]
+    StateCount {
+        state: String,
+        sender: oneshot::Sender<Result<u64, Error>>,
+    },
+
+    Raw {
+        request: serde_json::Value,
+        sender: oneshot::Sender<Result<serde_json::Value, Error>>,
+    },
+
+    Ping {
+        sender: oneshot::Sender<Result<(), Error>>,
+    },
+
+    StageStart {
+        name: String,
         sender: oneshot::Sender<Result<u64, Error>>,
     },
 
@@ -49,7 +135,8 @@ pub enum Command {
         sender: oneshot::Sender<Result<(), Error>>,
     },
 
-    WaitNetworkInitializedEnd {
+    StageEnd {
+        name: String,
         sender: oneshot::Sender<Result<u64, Error>>,
     },
 
@@ -73,10 +160,30 @@ pub enum Command {
         sender: oneshot::Sender<Result<u64, Error>>,
     },
 
+    #[cfg(feature = "metrics")]
     Metric {
         write_query: WriteQuery,
         sender: oneshot::Sender<Result<(), Error>>,
     },
+
+    #[cfg(feature = "metrics")]
+    MetricBatch {
+        write_queries: Vec<WriteQuery>,
+        sender: oneshot::Sender<Result<(), Error>>,
+    },
+
+    #[cfg(feature = "metrics")]
+    SetGlobalTags {
+        tags: HashMap<String, String>,
+    },
+
+    SetInstanceSeq {
+        seq: u64,
+    },
+
+    Shutdown {
+        sender: oneshot::Sender<Result<(), Error>>,
+    },
 }
 
 #[derive(Debug)]
@@ -88,104 +195,182 @@ enum PendingRequest {
         sender: oneshot::Sender<Result<(), Error>>,
     },
     Subscribe {
-        stream: mpsc::Sender<Result<serde_json::Value, Error>>,
+        /// Forwards to the stream of the subscriber, see [`forward`].
+        stream: mpsc::UnboundedSender<Result<serde_json::Value, Error>>,
+        /// Resolves once the subscriber dropped its stream.
+        dropped: oneshot::Receiver<()>,
+        cancel: Option<oneshot::Receiver<()>>,
+    },
+    /// Receives the response as is, see [`Command::Raw`].
+    Raw {
+        sender: oneshot::Sender<Result<serde_json::Value, Error>>,
+    },
+    /// Resolves on the pong answering the websocket ping, see [`Command::Ping`].
+    Ping {
+        sender: oneshot::Sender<Result<(), Error>>,
     },
 }
 
 pub struct BackgroundTask {
-    websocket_tx: soketto::Sender<Compat<tokio::net::TcpStream>>,
-    websocket_rx: futures::stream::BoxStream<'static, Result<Vec<u8>, soketto::connection::Error>>,
-
-    influxdb: Client,
+    websocket_tx: WebsocketTx,
+    websocket_rx: WebsocketRx,
+    /// The number of bytes written to the connection.
+    written: Arc<AtomicU64>,
+    /// `None` if the task shuts down once the connection is lost.
+    reconnect: Option<Reconnect>,
+    /// Pings the sync service periodically, if enabled.
+    keepalive: Option<tokio::time::Interval>,
+
+    /// `None` if metrics are disabled by the run, see `test_disable_metrics`,
+    /// or the URL if malformed, failing every write.
+    #[cfg(feature = "metrics")]
+    influxdb: Option<Result<Client, String>>,
+    /// Tags added to every metric, sorted by name.
+    #[cfg(feature = "metrics")]
+    global_tags: Vec<(String, String)>,
 
     next_id: u64,
 
     params: RunParameters,
+    /// The global sequence number of the instance, once assigned.
+    instance_seq: Option<u64>,
 
     client_rx: mpsc::Receiver<Command>,
+    /// Commands sent from outside an async context, see
+    /// [`BackgroundTask::blocking_sender`].
+    blocking_tx: mpsc::UnboundedSender<Command>,
+    blocking_rx: mpsc::UnboundedReceiver<Command>,
 
     pending_req: HashMap<u64, PendingRequest>,
 }
 
+type WebsocketTx = soketto::Sender<Compat<Box<dyn Socket>>>;
+type WebsocketRx = futures::stream::BoxStream<'static, Result<Frame, soketto::connection::Error>>;
+type Websocket = (WebsocketTx, WebsocketRx, Arc<AtomicU64>);
+
+/// A frame received from the sync service, see [`handshake`].
+enum Frame {
+    Data(Vec<u8>),
+    /// The payload of a pong, answering a ping of [`Command::Ping`] or of the
+    /// keepalive.
+    Pong(Vec<u8>),
+}
+
+/// How to reconnect to the sync service, see
+/// [`ClientBuilder::reconnect`](crate::client::ClientBuilder::reconnect).
+#[derive(Clone)]
+struct Reconnect {
+    sync_service: (String, u16),
+    handshake_timeout: Duration,
+    policy: ReconnectPolicy,
+}
+
 impl BackgroundTask {
+    /// Connects to the sync service, failing with
+    /// [`InitError::HandshakeTimeout`] if either establishing the connection or
+    /// the websocket handshake takes longer than `handshake_timeout`.
     pub async fn new(
+        sync_service: (&str, u16),
+        handshake_timeout: Duration,
         client_rx: mpsc::Receiver<Command>,
         params: RunParameters,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        let (websocket_tx, websocket_rx) = {
-            let socket = tokio::net::TcpStream::connect(("testground-sync-service", 5050)).await?;
-
-            let mut client = soketto::handshake::Client::new(socket.compat(), "...", "/");
-            match client.handshake().await? {
-                ServerResponse::Redirect {
-                    status_code,
-                    location,
-                } => {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!(
-                            "Remote redirected to {}. Status code {}",
-                            location, status_code
-                        ),
-                    )
-                    .into())
-                }
-                ServerResponse::Rejected { status_code } => {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::ConnectionRefused,
-                        format!("Remote refused connection. Status code {}", status_code),
-                    )
-                    .into())
-                }
-                _ => {}
-            };
-            let (tx, rx) = client.into_builder().finish();
+        let websocket = connect(sync_service, handshake_timeout).await?;
 
-            let socket_packets = futures::stream::unfold(rx, move |mut rx| async {
-                let mut buf = Vec::new();
-                let ret = match rx.receive_data(&mut buf).await {
-                    Ok(_) => Ok(buf),
-                    Err(err) => Err(err),
-                };
-                Some((ret, rx))
-            });
+        Ok(Self::with_websocket(websocket, client_rx, params))
+    }
 
-            (tx, socket_packets.boxed())
-        };
+    /// Connects to an in-process sync service, see `TESTGROUND_NO_SYNC`.
+    pub async fn local(
+        client_rx: mpsc::Receiver<Command>,
+        params: RunParameters,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let socket = LocalSyncService::new().connect();
+
+        Self::with_socket(Box::new(socket), client_rx, params).await
+    }
+
+    pub(crate) async fn with_socket(
+        socket: Box<dyn Socket>,
+        client_rx: mpsc::Receiver<Command>,
+        params: RunParameters,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let websocket = handshake(socket).await?;
+
+        Ok(Self::with_websocket(websocket, client_rx, params))
+    }
+
+    fn with_websocket(
+        (websocket_tx, websocket_rx, written): Websocket,
+        client_rx: mpsc::Receiver<Command>,
+        params: RunParameters,
+    ) -> Self {
+        #[cfg(feature = "metrics")]
+        let influxdb = (!params.test_disable_metrics).then(|| {
+            let url = params.influxdb_url.clone();
+            if valid_influxdb_url(&url) {
+                Ok(Client::new(url, "testground"))
+            } else {
+                log::warn!("Invalid InfluxDB URL {:?}, failing all metric writes", url);
+                Err(url)
+            }
+        });
 
-        let influxdb = Client::new(params.influxdb_url.clone(), "testground");
+        let (blocking_tx, blocking_rx) = mpsc::unbounded_channel();
 
-        Ok(Self {
+        Self {
             websocket_tx,
             websocket_rx,
+            written,
+            reconnect: None,
+            keepalive: None,
 
+            #[cfg(feature = "metrics")]
             influxdb,
+            #[cfg(feature = "metrics")]
+            global_tags: Vec::new(),
             next_id: 0,
             params,
+            instance_seq: None,
             client_rx,
+            blocking_tx,
+            blocking_rx,
             pending_req: Default::default(),
-        })
+        }
     }
 
-    fn contextualize_state(&self, state: &str) -> String {
-        format!(
-            "run:{}:plan:{}:case:{}:states:{}",
-            self.params.test_run, self.params.test_plan, self.params.test_case, state
-        )
+    /// Returns a sender of commands that never blocks, e.g. to record a crash
+    /// from a panicking thread, where awaiting a bounded channel isn't
+    /// possible.
+    pub fn blocking_sender(&self) -> mpsc::UnboundedSender<Command> {
+        self.blocking_tx.clone()
     }
 
-    fn contextualize_topic(&self, topic: &str) -> String {
-        format!(
-            "run:{}:plan:{}:case:{}:topics:{}",
-            self.params.test_run, self.params.test_plan, self.params.test_case, topic
-        )
+    /// Reconnects to `sync_service` according to `policy` once the connection
+    /// is lost, instead of shutting down.
+    pub fn reconnect(
+        mut self,
+        sync_service: (String, u16),
+        handshake_timeout: Duration,
+        policy: ReconnectPolicy,
+    ) -> Self {
+        self.reconnect = Some(Reconnect {
+            sync_service,
+            handshake_timeout,
+            policy,
+        });
+        self
     }
 
-    fn contextualize_event(&self) -> String {
-        format!(
-            "run:{}:plan:{}:case:{}:run_events",
-            self.params.test_run, self.params.test_plan, self.params.test_case
-        )
+    /// Pings the sync service every `interval`, so that intermediaries don't
+    /// close the connection while waiting, e.g. on a long barrier.
+    pub fn keepalive(mut self, interval: Duration) -> Self {
+        let mut keepalive =
+            tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        keepalive.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        self.keepalive = Some(keepalive);
+        self
     }
 
     fn next_id(&mut self) -> u64 {
@@ -199,28 +384,59 @@ impl BackgroundTask {
             tokio::select! {
                 res = self.websocket_rx.next() => match res {
                     Some(res) => match res {
-                        Ok(res) => self.response(serde_json::from_slice::<RawResponse>(&res).expect("Response Deserialization").into()).await,
+                        Ok(Frame::Data(res)) => self.received(&res).await,
+                        Ok(Frame::Pong(payload)) => self.pong(&payload),
                         Err(e) => {
-                            eprintln!("Web socket Error: {}", e);
-                            return;
+                            log::error!("Web socket Error: {}", e);
+                            self.disconnected();
+                            if !self.reconnected().await {
+                                return;
+                            }
                         }
                     },
                     None => {
-                        eprintln!("Web socket receiver dropped");
-                        return;
+                        log::error!("Web socket receiver dropped");
+                        self.disconnected();
+                        if !self.reconnected().await {
+                            return;
+                        }
                     },
                 },
                 cmd = self.client_rx.recv() => match cmd {
+                    Some(Command::Shutdown { sender }) => {
+                        log::debug!("Client requested shutdown. Closing web socket.");
+                        // Fail requests of other clones from now on.
+                        self.client_rx.close();
+                        self.flush_blocking().await;
+                        let _ = sender.send(self.websocket_tx.close().await.map_err(Into::into));
+                        return;
+                    },
                     Some(cmd) => self.command(cmd).await,
                     None => {
                         log::debug!("Client command sender dropped. Background task shutting down.");
+                        self.flush_blocking().await;
                         return;
                     },
                 },
+                // Never closed, as the task holds a sender itself.
+                Some(cmd) = self.blocking_rx.recv() => self.command(cmd).await,
+                id = Self::abandoned(&mut self.pending_req), if !self.pending_req.is_empty() => {
+                    self.cancel(id).await
+                },
+                () = Self::tick(&mut self.keepalive) => self.ping().await,
             }
         }
     }
 
+    /// Handles the commands of [`BackgroundTask::blocking_sender`] still
+    /// queued, e.g. a crash recorded by a panicking thread of a current-thread
+    /// runtime, which couldn't wait for it to be published.
+    async fn flush_blocking(&mut self) {
+        while let Ok(cmd) = self.blocking_rx.try_recv() {
+            self.command(cmd).await;
+        }
+    }
+
     async fn command(&mut self, cmd: Command) {
         let id = self.next_id();
 
@@ -230,43 +446,98 @@ impl BackgroundTask {
                 message,
                 sender,
             } => {
-                let topic = self.contextualize_topic(&topic);
+                let topic = context::topic(&self.params, &topic);
+
+                self.publish(id, topic, PayloadType::Json(message), sender)
+                    .await
+            }
+            Command::Subscribe {
+                topic,
+                stream,
+                cancel,
+            } => {
+                let topic = context::topic(&self.params, &topic);
+
+                self.subscribe(id, topic, stream, cancel).await
+            }
+            Command::SubscribeEvents { stream } => {
+                let topic = context::events(&self.params);
+
+                self.subscribe(id, topic, stream, None).await
+            }
+            Command::GroupPublish {
+                topic,
+                message,
+                sender,
+            } => {
+                let topic = context::group_topic(&self.params, &topic);
 
                 self.publish(id, topic, PayloadType::Json(message), sender)
                     .await
             }
-            Command::Subscribe { topic, stream } => {
-                let topic = self.contextualize_topic(&topic);
+            Command::GroupSubscribe { topic, stream } => {
+                let topic = context::group_topic(&self.params, &topic);
 
-                self.subscribe(id, topic, stream).await
+                self.subscribe(id, topic, stream, None).await
             }
             Command::SignalEntry { state, sender } => {
-                let state = self.contextualize_state(&state);
+                let state = context::state(&self.params, &state);
 
                 self.signal(id, state, sender).await
             }
+            #[cfg(any(test, feature = "testing"))]
+            Command::StateCount { state, sender } => {
+                let state = context::state(&self.params, &state);
+
+                self.state_count(id, state, sender).await
+            }
+            Command::Raw { request, sender } => {
+                let request = Request {
+                    id: id.to_string(),
+                    is_cancel: false,
+                    request: Some(RequestType::Raw(request)),
+                };
+
+                match self.send(request).await {
+                    Ok(()) => {
+                        self.pending_req.insert(id, PendingRequest::Raw { sender });
+                    }
+                    Err(e) => {
+                        let _ = sender.send(Err(e));
+                    }
+                }
+            }
             Command::Barrier {
                 state,
                 mut target,
                 sender,
             } => {
-                let state = self.contextualize_state(&state);
+                let state = context::state(&self.params, &state);
 
                 if target == 0 {
                     target = self.params.test_instance_count;
                 }
 
+                if target > self.params.global_instance_count() {
+                    log::warn!(
+                        "Barrier on {} targets {}, exceeding the {} instances of the run",
+                        state,
+                        target,
+                        self.params.global_instance_count()
+                    );
+                }
+
                 self.barrier(id, state, target, sender).await
             }
-            Command::WaitNetworkInitializedStart { sender } => {
+            Command::StageStart { name, sender } => {
                 let event = Event {
                     event: EventType::StageStart {
-                        name: "network-initialized".to_owned(),
+                        name,
                         group: self.params.test_group_id.clone(),
                     },
                 };
 
-                let topic = self.contextualize_event();
+                let topic = context::events(&self.params);
 
                 self.publish(id, topic, PayloadType::Event(event.event), sender)
                     .await
@@ -277,36 +548,38 @@ impl BackgroundTask {
                         "Running in environment without network side car. \
                         Skipping wait for network."
                     );
+                    let _ = sender.send(Ok(()));
                     return;
                 }
 
-                let state = self.contextualize_state("network-initialized");
+                let state = context::state(&self.params, "network-initialized");
                 let target = self.params.test_instance_count;
 
                 self.barrier(id, state, target, sender).await;
             }
-            Command::WaitNetworkInitializedEnd { sender } => {
+            Command::StageEnd { name, sender } => {
                 let event = Event {
                     event: EventType::StageEnd {
-                        name: "network-initialized".to_owned(),
+                        name,
                         group: self.params.test_group_id.clone(),
                     },
                 };
 
-                let topic = self.contextualize_event();
+                let topic = context::events(&self.params);
 
                 self.publish(id, topic, PayloadType::Event(event.event), sender)
                     .await
             }
             Command::NetworkShaping { config, sender } => {
-                if !self.params.test_sidecar {
-                    let _ = sender.send(Err(Error::SideCar));
+                let runner = self.params.runner_kind();
+                if runner == RunnerKind::LocalExec {
+                    let _ = sender.send(Err(Error::UnsupportedOnRunner(runner)));
                     return;
                 }
 
                 let topic = format!("network:{}", self.params.hostname);
 
-                let topic = self.contextualize_topic(&topic);
+                let topic = context::topic(&self.params, &topic);
 
                 self.publish(id, topic, PayloadType::Config(config), sender)
                     .await
@@ -316,7 +589,7 @@ impl BackgroundTask {
                     group: self.params.test_group_id.clone(),
                 };
 
-                let topic = self.contextualize_event();
+                let topic = context::events(&self.params);
 
                 self.publish(id, topic, PayloadType::Event(event), sender)
                     .await
@@ -327,7 +600,7 @@ impl BackgroundTask {
                     error,
                 };
 
-                let topic = self.contextualize_event();
+                let topic = context::events(&self.params);
 
                 self.publish(id, topic, PayloadType::Event(event), sender)
                     .await
@@ -343,18 +616,31 @@ impl BackgroundTask {
                     stacktrace,
                 };
 
-                let topic = self.contextualize_event();
+                let topic = context::events(&self.params);
 
                 self.publish(id, topic, PayloadType::Event(event), sender)
                     .await
             }
+            #[cfg(feature = "metrics")]
             Command::Metric {
                 write_query,
                 sender,
             } => {
-                //TODO add global tag to the query before processing
+                let influxdb = match self.influxdb() {
+                    Ok(Some(influxdb)) => influxdb,
+                    Ok(None) => {
+                        let _ = sender.send(Ok(()));
+                        return;
+                    }
+                    Err(e) => {
+                        let _ = sender.send(Err(e));
+                        return;
+                    }
+                };
+
+                let write_query = add_tags(write_query, &self.global_tags);
 
-                match self.influxdb.query(write_query).await {
+                match influxdb.query(write_query).await {
                     Ok(_) => {
                         let _ = sender.send(Ok(()));
                     }
@@ -363,6 +649,69 @@ impl BackgroundTask {
                     }
                 }
             }
+            #[cfg(feature = "metrics")]
+            Command::MetricBatch {
+                write_queries,
+                sender,
+            } => {
+                let influxdb = match self.influxdb() {
+                    Ok(Some(influxdb)) => influxdb,
+                    Ok(None) => {
+                        let _ = sender.send(Ok(()));
+                        return;
+                    }
+                    Err(e) => {
+                        let _ = sender.send(Err(e));
+                        return;
+                    }
+                };
+
+                let write_queries: Vec<_> = write_queries
+                    .into_iter()
+                    .map(|write_query| add_tags(write_query, &self.global_tags))
+                    .collect();
+
+                let res = match influxdb.query(&write_queries).await {
+                    Ok(_) => Ok(()),
+                    // Point out the culprit, as the batch fails as a whole.
+                    Err(e @ influxdb::Error::InvalidQueryError { .. }) => {
+                        match write_queries.iter().position(|q| q.build().is_err()) {
+                            Some(index) => Err(Error::InvalidMetric { index, source: e }),
+                            None => Err(e.into()),
+                        }
+                    }
+                    Err(e) => Err(e.into()),
+                };
+
+                let _ = sender.send(res);
+            }
+            #[cfg(feature = "metrics")]
+            Command::SetGlobalTags { tags } => {
+                let mut tags: Vec<_> = tags.into_iter().collect();
+                tags.sort();
+
+                self.global_tags = tags;
+            }
+            Command::SetInstanceSeq { seq } => self.instance_seq = Some(seq),
+            Command::Ping { sender } => match self.send_ping(&id.to_be_bytes()).await {
+                Ok(()) => {
+                    self.pending_req.insert(id, PendingRequest::Ping { sender });
+                }
+                Err(e) => {
+                    let _ = sender.send(Err(e.into()));
+                }
+            },
+            Command::Shutdown { .. } => unreachable!("Shutdown is handled by the run loop"),
+        }
+    }
+
+    /// The InfluxDB client, or `None` if metrics are disabled.
+    #[cfg(feature = "metrics")]
+    fn influxdb(&self) -> Result<Option<&Client>, Error> {
+        match &self.influxdb {
+            None => Ok(None),
+            Some(Ok(influxdb)) => Ok(Some(influxdb)),
+            Some(Err(url)) => Err(Error::InvalidInfluxUrl(url.clone())),
         }
     }
 
@@ -376,19 +725,25 @@ impl BackgroundTask {
         if let PayloadType::Event(ref event) = payload {
             // The Testground daemon determines the success or failure of a test
             // instance by parsing stdout for runtime events.
-            println!("{}", serde_json::to_string(&LogLine::new(event)).unwrap());
+            let line = LogLine::new(event, &self.params, self.instance_seq);
+            println!("{}", serde_json::to_string(&line).unwrap());
         }
 
         let request = Request {
             id: id.to_string(),
             is_cancel: false,
-            request: RequestType::Publish { topic, payload },
+            request: Some(RequestType::Publish { topic, payload }),
         };
 
-        self.send(request).await.expect(WEBSOCKET_RECEIVER);
-
-        self.pending_req
-            .insert(id, PendingRequest::PublishOrSignal { sender });
+        match self.send(request).await {
+            Ok(()) => {
+                self.pending_req
+                    .insert(id, PendingRequest::PublishOrSignal { sender });
+            }
+            Err(e) => {
+                let _ = sender.send(Err(e));
+            }
+        }
     }
 
     async fn subscribe(
@@ -396,17 +751,31 @@ impl BackgroundTask {
         id: u64,
         topic: String,
         stream: mpsc::Sender<Result<serde_json::Value, Error>>,
+        cancel: Option<oneshot::Receiver<()>>,
     ) {
         let request = Request {
             id: id.to_string(),
             is_cancel: false,
-            request: RequestType::Subscribe { topic },
+            request: Some(RequestType::Subscribe { topic }),
         };
 
-        self.send(request).await.expect(WEBSOCKET_RECEIVER);
+        match self.send(request).await {
+            Ok(()) => {
+                let (stream, dropped) = forward(stream);
 
-        self.pending_req
-            .insert(id, PendingRequest::Subscribe { stream });
+                self.pending_req.insert(
+                    id,
+                    PendingRequest::Subscribe {
+                        stream,
+                        dropped,
+                        cancel,
+                    },
+                );
+            }
+            Err(e) => {
+                let _ = stream.send(Err(e)).await;
+            }
+        }
     }
 
     async fn signal(
@@ -418,13 +787,42 @@ impl BackgroundTask {
         let request = Request {
             id: id.to_string(),
             is_cancel: false,
-            request: RequestType::SignalEntry { state },
+            request: Some(RequestType::SignalEntry { state }),
         };
 
-        self.send(request).await.expect(WEBSOCKET_RECEIVER);
+        match self.send(request).await {
+            Ok(()) => {
+                self.pending_req
+                    .insert(id, PendingRequest::PublishOrSignal { sender });
+            }
+            Err(e) => {
+                let _ = sender.send(Err(e));
+            }
+        }
+    }
+
+    #[cfg(any(test, feature = "testing"))]
+    async fn state_count(
+        &mut self,
+        id: u64,
+        state: String,
+        sender: oneshot::Sender<Result<u64, Error>>,
+    ) {
+        let request = Request {
+            id: id.to_string(),
+            is_cancel: false,
+            request: Some(RequestType::StateCount { state }),
+        };
 
-        self.pending_req
-            .insert(id, PendingRequest::PublishOrSignal { sender });
+        match self.send(request).await {
+            Ok(()) => {
+                self.pending_req
+                    .insert(id, PendingRequest::PublishOrSignal { sender });
+            }
+            Err(e) => {
+                let _ = sender.send(Err(e));
+            }
+        }
     }
 
     async fn barrier(
@@ -437,22 +835,234 @@ impl BackgroundTask {
         let request = Request {
             id: id.to_string(),
             is_cancel: false,
-            request: RequestType::Barrier { state, target },
+            request: Some(RequestType::Barrier { state, target }),
+        };
+
+        match self.send(request).await {
+            Ok(()) => {
+                self.pending_req
+                    .insert(id, PendingRequest::Barrier { sender });
+            }
+            Err(e) => {
+                let _ = sender.send(Err(e));
+            }
+        }
+    }
+
+    /// Resolves to the ID of a pending request no one is waiting for anymore,
+    /// e.g. the losing barriers of [`Client::barrier_any`](crate::client::Client::barrier_any)
+    /// or a cancelled subscription.
+    fn abandoned(pending_req: &mut HashMap<u64, PendingRequest>) -> impl Future<Output = u64> + '_ {
+        futures::future::poll_fn(move |cx| {
+            for (id, req) in pending_req.iter_mut() {
+                let closed = match req {
+                    PendingRequest::PublishOrSignal { sender } => sender.poll_closed(cx).is_ready(),
+                    PendingRequest::Barrier { sender } => sender.poll_closed(cx).is_ready(),
+                    PendingRequest::Raw { sender } => sender.poll_closed(cx).is_ready(),
+                    PendingRequest::Ping { sender } => sender.poll_closed(cx).is_ready(),
+                    PendingRequest::Subscribe {
+                        dropped, cancel, ..
+                    } => {
+                        // The subscriber dropped its stream.
+                        dropped.poll_unpin(cx).is_ready()
+                            || match cancel {
+                                Some(receiver) => match receiver.poll_unpin(cx) {
+                                    Poll::Ready(Ok(())) => true,
+                                    // The handle was dropped without cancelling.
+                                    Poll::Ready(Err(_)) => {
+                                        *cancel = None;
+                                        false
+                                    }
+                                    Poll::Pending => false,
+                                },
+                                None => false,
+                            }
+                    }
+                };
+
+                if closed {
+                    return Poll::Ready(*id);
+                }
+            }
+
+            Poll::Pending
+        })
+    }
+
+    /// Completes on the next tick of `keepalive`, or never if disabled.
+    async fn tick(keepalive: &mut Option<tokio::time::Interval>) {
+        match keepalive {
+            Some(keepalive) => {
+                keepalive.tick().await;
+            }
+            None => futures::future::pending().await,
+        }
+    }
+
+    /// Sends a websocket ping, with an empty payload, thus its pong resolves
+    /// no [`Command::Ping`].
+    async fn ping(&mut self) {
+        // A lost connection is noticed on the receiving half.
+        if let Err(e) = self.send_ping(&[]).await {
+            log::debug!("Failed to ping the sync service: {}", e);
+        }
+    }
+
+    /// Sends a websocket ping carrying `payload`, echoed by its pong.
+    async fn send_ping(&mut self, payload: &[u8]) -> Result<(), soketto::connection::Error> {
+        self.websocket_tx
+            .send_ping(payload.try_into().expect("Ping Payload"))
+            .await?;
+        self.websocket_tx.flush().await
+    }
+
+    /// Resolves the [`Command::Ping`] answered by a pong, if any, i.e. not for
+    /// pongs of the keepalive.
+    fn pong(&mut self, payload: &[u8]) {
+        let idx = match <[u8; 8]>::try_from(payload) {
+            Ok(id) => u64::from_be_bytes(id),
+            Err(_) => return,
+        };
+
+        if let Some(PendingRequest::Ping { .. }) = self.pending_req.get(&idx) {
+            if let Some(PendingRequest::Ping { sender }) = self.pending_req.remove(&idx) {
+                let _ = sender.send(Ok(()));
+            }
+        }
+    }
+
+    /// Fails all pending requests once the connection to the sync service is
+    /// lost.
+    fn disconnected(&mut self) {
+        for (_, req) in self.pending_req.drain() {
+            match req {
+                PendingRequest::PublishOrSignal { sender } => {
+                    let _ = sender.send(Err(Error::Disconnected));
+                }
+                PendingRequest::Barrier { sender } => {
+                    let _ = sender.send(Err(Error::Disconnected));
+                }
+                PendingRequest::Subscribe { stream, .. } => {
+                    let _ = stream.send(Err(Error::Disconnected));
+                }
+                PendingRequest::Raw { sender } => {
+                    let _ = sender.send(Err(Error::Disconnected));
+                }
+                PendingRequest::Ping { sender } => {
+                    let _ = sender.send(Err(Error::Disconnected));
+                }
+            }
+        }
+    }
+
+    /// Tries to re-establish the connection to the sync service according to
+    /// the [`ReconnectPolicy`], backing off exponentially between attempts.
+    ///
+    /// Returns whether the connection was re-established.
+    async fn reconnected(&mut self) -> bool {
+        let reconnect = match &self.reconnect {
+            Some(reconnect) => reconnect.clone(),
+            None => return false,
+        };
+
+        let ReconnectPolicy {
+            max_attempts,
+            initial_backoff,
+            max_backoff,
+        } = reconnect.policy;
+        let (host, port) = &reconnect.sync_service;
+
+        let mut backoff = initial_backoff;
+
+        for attempt in 1..=max_attempts {
+            tokio::time::sleep(backoff).await;
+
+            match connect((host.as_str(), *port), reconnect.handshake_timeout).await {
+                Ok((websocket_tx, websocket_rx, written)) => {
+                    log::info!("Reconnected to the sync service after {} attempts", attempt);
+                    self.websocket_tx = websocket_tx;
+                    self.websocket_rx = websocket_rx;
+                    self.written = written;
+                    return true;
+                }
+                Err(e) => log::warn!("Failed to reconnect to the sync service: {}", e),
+            }
+
+            backoff = (backoff * 2).min(max_backoff);
+        }
+
+        log::error!(
+            "Giving up reconnecting to the sync service after {} attempts",
+            max_attempts
+        );
+        false
+    }
+
+    /// Drops a pending request and asks the sync service to cancel it.
+    async fn cancel(&mut self, id: u64) {
+        // Pings aren't requests of the sync service, thus have nothing to cancel.
+        if let Some(PendingRequest::Ping { .. }) = self.pending_req.remove(&id) {
+            return;
+        }
+
+        let request = Request {
+            id: id.to_string(),
+            is_cancel: true,
+            request: None,
+        };
+
+        if let Err(e) = self.send(request).await {
+            log::debug!("Failed to cancel request {}: {}", id, e);
+        }
+    }
+
+    /// Handles a message of the sync service, passing responses to raw
+    /// requests on as is.
+    async fn received(&mut self, res: &[u8]) {
+        let res = match serde_json::from_slice::<serde_json::Value>(res) {
+            Ok(res) => res,
+            Err(e) => {
+                log::error!("Ignoring response: {}", e);
+                return;
+            }
         };
 
-        self.send(request).await.expect(WEBSOCKET_RECEIVER);
+        let idx = res
+            .get("id")
+            .and_then(serde_json::Value::as_str)
+            .and_then(|id| id.parse::<u64>().ok());
+        if let Some(idx) = idx {
+            if let Some(PendingRequest::Raw { .. }) = self.pending_req.get(&idx) {
+                if let Some(PendingRequest::Raw { sender }) = self.pending_req.remove(&idx) {
+                    let _ = sender.send(Ok(res));
+                }
+                return;
+            }
+        }
 
-        self.pending_req
-            .insert(id, PendingRequest::Barrier { sender });
+        match serde_json::from_value::<RawResponse>(res)
+            .map_err(Error::from)
+            .and_then(Response::try_from)
+        {
+            Ok(res) => self.response(res).await,
+            Err(e) => log::error!("Ignoring response: {}", e),
+        }
     }
 
     async fn response(&mut self, res: Response) {
         let Response { id, response } = res;
 
-        let idx = id.parse().unwrap();
+        let idx = match id.parse::<u64>() {
+            Ok(idx) => idx,
+            Err(e) => {
+                log::error!("Ignoring response with malformed id {:?}: {}", id, e);
+                return;
+            }
+        };
 
         let pending_req = match self.pending_req.remove(&idx) {
             Some(req) => req,
+            // E.g. a late response to a cancelled request.
             None => return,
         };
 
@@ -463,13 +1073,26 @@ impl BackgroundTask {
             (PendingRequest::PublishOrSignal { sender }, ResponseType::Error(error)) => {
                 let _ = sender.send(Err(Error::SyncService(error)));
             }
-            (PendingRequest::Subscribe { stream }, ResponseType::Error(error)) => {
-                let _ = stream.send(Err(Error::SyncService(error))).await;
+            (PendingRequest::Subscribe { stream, .. }, ResponseType::Error(error)) => {
+                let _ = stream.send(Err(Error::SyncService(error)));
             }
-            (PendingRequest::Subscribe { stream }, ResponseType::Subscribe(msg)) => {
-                if stream.send(Ok(msg)).await.is_ok() {
-                    self.pending_req
-                        .insert(idx, PendingRequest::Subscribe { stream });
+            (
+                PendingRequest::Subscribe {
+                    stream,
+                    dropped,
+                    cancel,
+                },
+                ResponseType::Subscribe(msg),
+            ) => {
+                if stream.send(Ok(msg)).is_ok() {
+                    self.pending_req.insert(
+                        idx,
+                        PendingRequest::Subscribe {
+                            stream,
+                            dropped,
+                            cancel,
+                        },
+                    );
                 }
             }
             (PendingRequest::PublishOrSignal { sender }, ResponseType::SignalEntry { seq }) => {
@@ -478,22 +1101,545 @@ impl BackgroundTask {
             (PendingRequest::PublishOrSignal { sender }, ResponseType::Publish { seq }) => {
                 let _ = sender.send(Ok(seq));
             }
+            (PendingRequest::PublishOrSignal { sender }, ResponseType::StateCount { count }) => {
+                let _ = sender.send(Ok(count));
+            }
             (PendingRequest::Barrier { sender }, ResponseType::Barrier) => {
                 let _ = sender.send(Ok(()));
             }
+            (req, ResponseType::Unknown(res)) => {
+                // E.g. a response of a newer sync service, which may still
+                // send a response this SDK understands.
+                log::warn!("Ignoring unknown response {} to request {:?}", res, req);
+                self.pending_req.insert(idx, req);
+            }
             (req, res) => {
-                panic!("No match Request: {:?} Response: {:?}", req, res);
+                // The response is bogus rather than the request, which thus
+                // stays pending for its actual response.
+                log::error!("Ignoring response {:?} not matching request {:?}", res, req);
+                self.pending_req.insert(idx, req);
+            }
+        }
+    }
+
+    /// Sends a request to the sync service.
+    ///
+    /// A failure only affects the request at hand, leaving it to the caller to
+    /// report it to whoever is waiting on the request, unless reconnecting,
+    /// see [`ReconnectPolicy`], which fails all pending requests.
+    ///
+    /// Fails with [`Error::NotSent`] if none of the request was written and
+    /// the connection was re-established, thus the request is safe to retry.
+    /// A request written partially may have reached the sync service, e.g.
+    /// when failing to flush, thus fails with [`Error::Soketto`].
+    async fn send(&mut self, req: Request) -> Result<(), Error> {
+        let mut json = serde_json::to_vec(&req)?;
+
+        let written = self.written.load(Ordering::Relaxed);
+
+        let res = match self.websocket_tx.send_binary_mut(&mut json).await {
+            Ok(()) => self.websocket_tx.flush().await,
+            Err(e) => Err(e),
+        };
+
+        let e = match res {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+
+        let not_sent = self.written.load(Ordering::Relaxed) == written;
+
+        // The connection is broken, if not desynchronized by a partial frame.
+        if self.reconnect.is_some() {
+            self.disconnected();
+            if self.reconnected().await && not_sent {
+                return Err(Error::NotSent(e));
             }
         }
+
+        Err(e.into())
+    }
+}
+
+/// Connects to the sync service, see [`BackgroundTask::new`].
+async fn connect(
+    sync_service: (&str, u16),
+    handshake_timeout: Duration,
+) -> Result<Websocket, Box<dyn std::error::Error>> {
+    let connect = async {
+        let socket = tokio::net::TcpStream::connect(sync_service).await?;
+
+        handshake(Box::new(socket)).await
+    };
+
+    match tokio::time::timeout(handshake_timeout, connect).await {
+        Ok(res) => res,
+        Err(_) => Err(InitError::HandshakeTimeout(handshake_timeout).into()),
     }
+}
+
+async fn handshake(socket: Box<dyn Socket>) -> Result<Websocket, Box<dyn std::error::Error>> {
+    let written = Arc::new(AtomicU64::new(0));
+    let socket: Box<dyn Socket> = Box::new(Counted {
+        inner: socket,
+        written: written.clone(),
+    });
+
+    let mut client = soketto::handshake::Client::new(socket.compat(), "...", "/");
+    match client.handshake().await? {
+        ServerResponse::Redirect {
+            status_code,
+            location,
+        } => {
+            return Err(std::io::Error::other(format!(
+                "Remote redirected to {}. Status code {}",
+                location, status_code
+            ))
+            .into())
+        }
+        ServerResponse::Rejected { status_code } => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                format!("Remote refused connection. Status code {}", status_code),
+            )
+            .into())
+        }
+        _ => {}
+    };
+    let (tx, rx) = client.into_builder().finish();
 
-    async fn send(&mut self, req: Request) -> Result<(), ()> {
-        let mut json = serde_json::to_vec(&req).expect("Request Serialization");
+    // Control frames, i.e. pings and close frames, are handled by `receive`,
+    // thus only text and binary frames, and pongs are yielded.
+    let socket_packets = futures::stream::unfold(rx, move |mut rx| async {
+        loop {
+            let mut buf = Vec::new();
+            let ret = match rx.receive(&mut buf).await {
+                // Skip empty frames, e.g. keepalives of intermediaries, rather
+                // than failing to deserialize them.
+                Ok(Incoming::Data(_)) if buf.is_empty() => continue,
+                Ok(Incoming::Data(_)) => Ok(Frame::Data(buf)),
+                Ok(Incoming::Pong(payload)) => Ok(Frame::Pong(payload.to_vec())),
+                // Receiving again fails, the connection being closed.
+                Ok(Incoming::Closed(_)) => continue,
+                Err(err) => Err(err),
+            };
+            return Some((ret, rx));
+        }
+    });
+
+    Ok((tx, socket_packets.boxed(), written))
+}
+
+/// Forwards the elements of a subscription to the stream of the subscriber
+/// from a separate task, buffering elements while the stream is at capacity.
+///
+/// Thus a slow subscriber doesn't block the background task, i.e. all other
+/// requests, e.g. a barrier the subscriber waits on before consuming.
+///
+/// The returned receiver resolves once the subscriber dropped its stream,
+/// allowing to cancel the subscription right away rather than on its next
+/// element.
+fn forward(
+    stream: mpsc::Sender<Result<serde_json::Value, Error>>,
+) -> (
+    mpsc::UnboundedSender<Result<serde_json::Value, Error>>,
+    oneshot::Receiver<()>,
+) {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let (dropped_tx, dropped) = oneshot::channel();
+
+    tokio::spawn(async move {
+        // Dropped once the subscriber is gone, resolving `dropped`.
+        let _dropped_tx = dropped_tx;
+
+        loop {
+            let item = tokio::select! {
+                item = receiver.recv() => match item {
+                    Some(item) => item,
+                    None => return,
+                },
+                () = stream.closed() => return,
+            };
 
-        self.websocket_tx.send_binary_mut(&mut json).await.unwrap();
+            if stream.send(item).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    (sender, dropped)
+}
+
+/// Whether `url` is an HTTP(S) URL with a host, e.g.
+/// `http://testground-influxdb:8086`.
+#[cfg(feature = "metrics")]
+fn valid_influxdb_url(url: &str) -> bool {
+    let rest = match url
+        .strip_prefix("http://")
+        .or_else(|| url.strip_prefix("https://"))
+    {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+
+    match authority.rsplit_once(':') {
+        // Not the colon of a bracketed IPv6 address.
+        Some((host, port)) if !port.contains(']') => {
+            !host.is_empty() && port.parse::<u16>().is_ok()
+        }
+        _ => !authority.is_empty(),
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn add_tags(mut write_query: WriteQuery, tags: &[(String, String)]) -> WriteQuery {
+    for (tag, value) in tags {
+        write_query = write_query.add_tag(tag.clone(), value.clone());
+    }
 
-        self.websocket_tx.flush().await.unwrap();
+    write_query
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::pin::Pin;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+    use std::task::{Context, Poll};
+
+    use tokio::io::ReadBuf;
+    use tokio::net::TcpStream;
+
+    use super::*;
+    use crate::{
+        client::{ClientBuilder, RetryPolicy},
+        params::test_run_parameters,
+        testing::MockSyncService,
+    };
+
+    /// A socket failing all writes while `fail` is set, but the first one if
+    /// `partial` is set, writing a single byte.
+    struct FlakySocket {
+        inner: TcpStream,
+        fail: Arc<AtomicBool>,
+        partial: Arc<AtomicBool>,
+    }
+
+    impl AsyncRead for FlakySocket {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for FlakySocket {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            if !self.fail.load(Ordering::SeqCst) {
+                return Pin::new(&mut self.inner).poll_write(cx, buf);
+            }
+            if !buf.is_empty() && self.partial.swap(false, Ordering::SeqCst) {
+                return Pin::new(&mut self.inner).poll_write(cx, &buf[..1]);
+            }
+            Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_flush(cx)
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_shutdown(cx)
+        }
+    }
+
+    #[tokio::test]
+    async fn send_failure_only_fails_its_request() {
+        let sync_service = MockSyncService::start().await;
+        let fail = Arc::new(AtomicBool::new(false));
+        let socket = FlakySocket {
+            inner: TcpStream::connect(sync_service.addr()).await.unwrap(),
+            fail: fail.clone(),
+            partial: Default::default(),
+        };
+
+        let (cmd_tx, cmd_rx) = mpsc::channel(1);
+        let background =
+            BackgroundTask::with_socket(Box::new(socket), cmd_rx, test_run_parameters())
+                .await
+                .unwrap();
+        tokio::spawn(background.run());
+
+        let (sender, barrier) = oneshot::channel();
+        cmd_tx
+            .send(Command::Barrier {
+                state: "ready".to_owned(),
+                target: 1,
+                sender,
+            })
+            .await
+            .unwrap();
+
+        // Commands are handled in order, thus once this signal resolves, the
+        // barrier request is on the wire.
+        let (sender, signal) = oneshot::channel();
+        cmd_tx
+            .send(Command::SignalEntry {
+                state: "warmup".to_owned(),
+                sender,
+            })
+            .await
+            .unwrap();
+        signal.await.unwrap().unwrap();
+
+        fail.store(true, Ordering::SeqCst);
+        let (sender, failed) = oneshot::channel();
+        cmd_tx
+            .send(Command::SignalEntry {
+                state: "other".to_owned(),
+                sender,
+            })
+            .await
+            .unwrap();
+        assert!(matches!(failed.await.unwrap(), Err(Error::Soketto(_))));
+
+        let (sender, failed) = oneshot::channel();
+        cmd_tx
+            .send(Command::Publish {
+                topic: "other".to_owned(),
+                message: serde_json::json!("other"),
+                sender,
+            })
+            .await
+            .unwrap();
+        assert!(matches!(failed.await.unwrap(), Err(Error::Soketto(_))));
+
+        let (sender, failed) = oneshot::channel();
+        cmd_tx
+            .send(Command::Barrier {
+                state: "other".to_owned(),
+                target: 1,
+                sender,
+            })
+            .await
+            .unwrap();
+        assert!(matches!(failed.await.unwrap(), Err(Error::Soketto(_))));
+        fail.store(false, Ordering::SeqCst);
+
+        let (sender, signal) = oneshot::channel();
+        cmd_tx
+            .send(Command::SignalEntry {
+                state: "ready".to_owned(),
+                sender,
+            })
+            .await
+            .unwrap();
+        assert_eq!(signal.await.unwrap().unwrap(), 1);
+        barrier.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn retry_requests_not_sent() {
+        let sync_service = MockSyncService::start().await;
+        let addr = sync_service.addr();
+
+        let client = |socket: FlakySocket| {
+            ClientBuilder::new()
+                .run_parameters(test_run_parameters())
+                .sync_service_addr(addr.ip().to_string(), addr.port())
+                .reconnect(ReconnectPolicy {
+                    max_attempts: 3,
+                    initial_backoff: Duration::from_millis(10),
+                    max_backoff: Duration::from_millis(10),
+                })
+                .retry(RetryPolicy {
+                    max_attempts: 3,
+                    initial_backoff: Duration::from_millis(1),
+                    max_backoff: Duration::from_millis(1),
+                })
+                .build_with_socket(Box::new(socket))
+        };
+
+        let fail = Arc::new(AtomicBool::new(false));
+        let flaky = client(FlakySocket {
+            inner: TcpStream::connect(addr).await.unwrap(),
+            fail: fail.clone(),
+            partial: Default::default(),
+        })
+        .await
+        .unwrap();
+
+        // Nothing is written, thus the signal is retried once reconnected.
+        fail.store(true, Ordering::SeqCst);
+        assert_eq!(flaky.signal_entry("ready").await.unwrap(), 1);
+        flaky.barrier("ready", 1).await.unwrap();
+        assert_eq!(flaky.state_count("ready").await.unwrap(), 1);
+
+        // The signal may have reached the sync service, thus isn't retried.
+        let fail = Arc::new(AtomicBool::new(false));
+        let partial = Arc::new(AtomicBool::new(true));
+        let flaky = client(FlakySocket {
+            inner: TcpStream::connect(addr).await.unwrap(),
+            fail: fail.clone(),
+            partial,
+        })
+        .await
+        .unwrap();
+        fail.store(true, Ordering::SeqCst);
+        assert!(matches!(
+            flaky.signal_entry("partial").await,
+            Err(Error::Soketto(_))
+        ));
+        assert_eq!(flaky.state_count("partial").await.unwrap(), 0);
+        assert_eq!(flaky.state_count("ready").await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn ignore_unexpected_responses() {
+        let sync_service = MockSyncService::start().await;
+        let socket = TcpStream::connect(sync_service.addr()).await.unwrap();
+
+        let (_cmd_tx, cmd_rx) = mpsc::channel(1);
+        let mut background =
+            BackgroundTask::with_socket(Box::new(socket), cmd_rx, test_run_parameters())
+                .await
+                .unwrap();
+
+        let (sender, mut barrier) = oneshot::channel();
+        background
+            .pending_req
+            .insert(0, PendingRequest::Barrier { sender });
+
+        background
+            .response(Response {
+                id: "not-a-number".to_owned(),
+                response: ResponseType::Barrier,
+            })
+            .await;
+        background
+            .response(Response {
+                id: "0".to_owned(),
+                response: ResponseType::Publish { seq: 1 },
+            })
+            .await;
+        background
+            .response(Response {
+                id: "0".to_owned(),
+                response: ResponseType::Unknown(serde_json::json!({"lock": {"holder": 2}})),
+            })
+            .await;
+        assert!(barrier.try_recv().is_err());
+
+        background
+            .response(Response {
+                id: "0".to_owned(),
+                response: ResponseType::Barrier,
+            })
+            .await;
+        barrier.try_recv().unwrap().unwrap();
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn metrics_written_before_outcome() {
+        let sync_service = MockSyncService::start().await;
+        let socket = TcpStream::connect(sync_service.addr()).await.unwrap();
+
+        let mut params = test_run_parameters();
+        // Nothing listens there, failing the write once attempted.
+        params.influxdb_url = "http://127.0.0.1:1".to_owned();
+
+        let (cmd_tx, cmd_rx) = mpsc::channel(2);
+        let background = BackgroundTask::with_socket(Box::new(socket), cmd_rx, params)
+            .await
+            .unwrap();
+        tokio::spawn(background.run());
+
+        let (sender, mut metric) = oneshot::channel();
+        cmd_tx
+            .send(Command::Metric {
+                write_query: WriteQuery::new(influxdb::Timestamp::Nanoseconds(0), "latency")
+                    .add_field("ms", 1),
+                sender,
+            })
+            .await
+            .unwrap();
+
+        let (sender, success) = oneshot::channel();
+        cmd_tx
+            .send(Command::SignalSuccess { sender })
+            .await
+            .unwrap();
+
+        success.await.unwrap().unwrap();
+        assert!(metric.try_recv().unwrap().is_err());
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn invalid_influxdb_url() {
+        assert!(valid_influxdb_url("http://testground-influxdb:8086"));
+        assert!(valid_influxdb_url("https://[::1]:8086/"));
+        assert!(valid_influxdb_url("http://localhost"));
+        assert!(!valid_influxdb_url(""));
+        assert!(!valid_influxdb_url("testground-influxdb:8086"));
+        assert!(!valid_influxdb_url("http://:8086"));
+        assert!(!valid_influxdb_url("http://testground-influxdb:influx"));
+
+        let sync_service = MockSyncService::start().await;
+        let socket = TcpStream::connect(sync_service.addr()).await.unwrap();
+
+        let mut params = test_run_parameters();
+        params.influxdb_url = String::new();
+
+        let (cmd_tx, cmd_rx) = mpsc::channel(1);
+        let background = BackgroundTask::with_socket(Box::new(socket), cmd_rx, params)
+            .await
+            .unwrap();
+        tokio::spawn(background.run());
+
+        let (sender, metric) = oneshot::channel();
+        cmd_tx
+            .send(Command::Metric {
+                write_query: WriteQuery::new(influxdb::Timestamp::Nanoseconds(0), "latency")
+                    .add_field("ms", 1),
+                sender,
+            })
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            metric.await.unwrap(),
+            Err(Error::InvalidInfluxUrl(url)) if url.is_empty()
+        ));
+    }
 
-        Ok(())
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn add_global_tags() {
+        let tags = [
+            ("group_id".to_owned(), "single".to_owned()),
+            ("run_id".to_owned(), "c7fjstge5te621cen4i0".to_owned()),
+        ];
+
+        let write_query = WriteQuery::new(influxdb::Timestamp::Nanoseconds(0), "latency")
+            .add_field("ms", 1)
+            .add_tag("peer", "a");
+
+        assert_eq!(
+            add_tags(write_query, &tags).build().unwrap().get(),
+            "latency,peer=a,group_id=single,run_id=c7fjstge5te621cen4i0 ms=1i 0"
+        );
     }
 }
@@ -0,0 +1,194 @@
+//! Splitting of large payloads into ordered fragments, published as separate
+//! items of a topic, and their reassembly on the subscribing side.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::errors::Error;
+
+const HEADER: &str = "__testground_chunk";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Fragment {
+    #[serde(rename = "__testground_chunk")]
+    header: Header,
+    data: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Header {
+    id: String,
+    index: usize,
+    count: usize,
+}
+
+/// Splits payloads exceeding a threshold into fragments.
+#[derive(Debug)]
+pub struct Chunker {
+    threshold: usize,
+    next_id: AtomicU64,
+}
+
+impl Chunker {
+    /// Splits payloads whose JSON encoding exceeds `threshold` bytes.
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the items to publish for `message`, i.e. either `message`
+    /// itself or its fragments, each carrying at most `threshold` bytes of its
+    /// JSON encoding.
+    ///
+    /// `global_seq` makes fragment IDs unique across all instances of a run.
+    pub fn split(&self, global_seq: u64, message: Value) -> Vec<Value> {
+        let encoded = message.to_string();
+
+        if encoded.len() <= self.threshold {
+            return vec![message];
+        }
+
+        let id = format!(
+            "{}:{}",
+            global_seq,
+            self.next_id.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let mut pieces = Vec::new();
+        let mut rest = encoded.as_str();
+        while !rest.is_empty() {
+            // Split on a character boundary, taking at least one character.
+            let mut end = self.threshold.min(rest.len());
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+            if end == 0 {
+                end = rest.chars().next().map(char::len_utf8).unwrap_or_default();
+            }
+
+            let (piece, tail) = rest.split_at(end);
+            pieces.push(piece);
+            rest = tail;
+        }
+
+        let count = pieces.len();
+
+        pieces
+            .into_iter()
+            .enumerate()
+            .map(|(index, data)| {
+                let fragment = Fragment {
+                    header: Header {
+                        id: id.clone(),
+                        index,
+                        count,
+                    },
+                    data: data.to_owned(),
+                };
+
+                serde_json::to_value(fragment).expect("Fragment Serialization")
+            })
+            .collect()
+    }
+}
+
+/// Reassembles the fragments of `stream`, yielding each payload once its last
+/// fragment arrived. Items that aren't fragments are passed through.
+///
+/// Fragments of a payload are expected in order, as published by
+/// [`Chunker::split`]. A fragment out of order, e.g. following a lost one,
+/// yields an [`Error::Reassembly`] and drops the partially reassembled
+/// payload.
+pub fn reassemble<S>(stream: S) -> impl Stream<Item = Result<Value, Error>>
+where
+    S: Stream<Item = Result<Value, Error>>,
+{
+    let mut partial: HashMap<String, (usize, String)> = HashMap::new();
+
+    stream.filter_map(move |item| {
+        futures::future::ready(match item {
+            Ok(item) if item.get(HEADER).is_some() => reassemble_fragment(&mut partial, item),
+            item => Some(item),
+        })
+    })
+}
+
+fn reassemble_fragment(
+    partial: &mut HashMap<String, (usize, String)>,
+    item: Value,
+) -> Option<Result<Value, Error>> {
+    let Fragment { header, data } = match serde_json::from_value(item) {
+        Ok(fragment) => fragment,
+        Err(e) => return Some(Err(e.into())),
+    };
+
+    let (next, encoded) = partial.entry(header.id.clone()).or_default();
+
+    if header.index != *next {
+        let expected = *next;
+        partial.remove(&header.id);
+        return Some(Err(Error::Reassembly(format!(
+            "fragment {} of {} arrived while expecting fragment {}",
+            header.index, header.id, expected
+        ))));
+    }
+
+    *next += 1;
+    encoded.push_str(&data);
+
+    if *next < header.count {
+        return None;
+    }
+
+    let (_, encoded) = partial.remove(&header.id).expect("Partial Payload");
+
+    Some(serde_json::from_str(&encoded).map_err(Into::into))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn round_trip() {
+        let chunker = Chunker::new(16);
+
+        let large = serde_json::json!({ "peers": vec!["ünïcödé"; 8] });
+        let small = serde_json::json!("small");
+
+        let mut items = chunker.split(1, large.clone());
+        assert!(items.len() > 1);
+        assert!(items
+            .iter()
+            .all(|item| item[HEADER]["count"] == items.len()));
+        items.extend(chunker.split(1, small.clone()));
+
+        let items: Vec<_> = reassemble(futures::stream::iter(items.into_iter().map(Ok)))
+            .collect()
+            .await;
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].as_ref().unwrap(), &large);
+        assert_eq!(items[1].as_ref().unwrap(), &small);
+    }
+
+    #[tokio::test]
+    async fn lost_fragment() {
+        let chunker = Chunker::new(4);
+
+        let mut items = chunker.split(1, serde_json::json!("lost fragment"));
+        items.remove(1);
+
+        let items: Vec<_> = reassemble(futures::stream::iter(items.into_iter().map(Ok)))
+            .collect()
+            .await;
+
+        assert!(matches!(items[0], Err(Error::Reassembly(_))));
+    }
+}
@@ -1,73 +1,390 @@
 use std::borrow::Cow;
-use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Mutex, MutexGuard, PoisonError,
+};
+use std::thread::ThreadId;
+use std::time::{Duration, SystemTime};
 
 use crate::{
     background::{BackgroundTask, Command},
-    errors::Error,
-    events::{Event, EventType},
-    network_conf::NetworkConfiguration,
+    chunking::{self, Chunker},
+    csv,
+    errors::{Error, InitError},
+    events::{EventType, Outcome},
+    logger::TestgroundLogger,
+    metrics::{self, FieldValue, Histogram},
+    network_conf::{
+        FilterAction, LinkShape, LinkShapeBuilder, NetworkConfiguration,
+        NetworkConfigurationBuilder,
+    },
+    run_out::{Rotation, RunOut},
+    subscription::{self, Subscription},
+    summary::{OutcomeKind, RunSummary, Stats},
     RunParameters,
 };
 
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use futures::future::Either;
+use serde::{de::DeserializeOwned, Serialize};
+
 use clap::Parser;
 
-use influxdb::WriteQuery;
+use crate::{Timestamp, WriteQuery};
 
 use crate::events::LogLine;
 use tokio::sync::{
-    mpsc::{self, channel, Sender},
+    mpsc::{self, channel, error::SendError, Sender},
     oneshot,
 };
-use tokio_stream::{wrappers::ReceiverStream, Stream};
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
 
-const BACKGROUND_RECEIVER: &str = "Background Receiver";
-const BACKGROUND_SENDER: &str = "Background Sender";
+/// How long to wait for the sync service to acknowledge a crash recorded
+/// outside an async context, see [`Client::failure_guard`].
+const BLOCKING_CRASH_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Basic synchronization client enabling one to send signals, await barriers and subscribe or publish to a topic.
+///
+/// Clones of a client share its connection to the sync service, which is
+/// closed once the last clone is dropped or [`Client::close`] is called. The
+/// outcome of the instance is recorded at most once across all clones: once a
+/// clone called [`Client::record_success`], [`Client::record_failure`] or
+/// [`Client::record_crash`], recording another outcome fails with
+/// [`Error::OutcomeAlreadyRecorded`], and once the connection is closed,
+/// recording one fails with [`Error::Closed`].
 #[derive(Clone)]
 pub struct Client {
     cmd_tx: Sender<Command>,
+    /// Sends commands from outside an async context, see
+    /// [`Client::failure_guard`].
+    blocking_tx: mpsc::UnboundedSender<Command>,
     /// The runtime parameters for this test.
     run_parameters: RunParameters,
     /// A global sequence number assigned to this test instance by the sync service.
     global_seq: u64,
     /// A group-scoped sequence number assigned to this test instance by the sync service.
     group_seq: u64,
-    /// A path to `run.out`.
-    run_out: Option<PathBuf>,
+    /// The writer of `run.out`.
+    run_out: Option<Arc<RunOut>>,
+    /// Whether to write `summary.json` once the outcome is recorded.
+    write_summary: bool,
+    /// Statistics shared between all clones of this client.
+    stats: Arc<Stats>,
+    /// Splits large payloads before publishing, if enabled.
+    chunker: Option<Arc<Chunker>>,
+    /// The runtime driving the background task, if owned by the client, see
+    /// [`Client::new_and_init_blocking`].
+    runtime: Option<Arc<OwnedRuntime>>,
+    /// How to retry signals and barriers failing to be sent, if at all.
+    retry: Option<RetryPolicy>,
+    /// The number of network configurations applied by
+    /// [`Client::disconnect_network`] and [`Client::reconnect_network`].
+    network_changes: Arc<AtomicU64>,
+    /// Whether a clone of this client recorded the outcome of the instance.
+    outcome_recorded: Arc<AtomicBool>,
+    /// The panics captured for the guards of [`Client::failure_guard`].
+    panics: Arc<PanicCapture>,
 }
 
-impl Client {
-    pub async fn new_and_init() -> Result<Self, Box<dyn std::error::Error>> {
-        let run_parameters: RunParameters = RunParameters::try_parse()?;
+/// Builder for a [`Client`].
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    run_parameters: Option<RunParameters>,
+    sync_service: Option<(String, u16)>,
+    handshake_timeout: Duration,
+    run_out_rotation: Option<Rotation>,
+    write_summary: bool,
+    chunk_threshold: Option<usize>,
+    reconnect: Option<ReconnectPolicy>,
+    keepalive: Option<Duration>,
+    retry: Option<RetryPolicy>,
+    command_buffer: usize,
+}
+
+/// How to reconnect to the sync service once the connection is lost, see
+/// [`ClientBuilder::reconnect`].
+#[derive(Debug, Clone, Copy)]
+pub struct ReconnectPolicy {
+    /// The number of attempts before giving up.
+    pub max_attempts: u32,
+    /// The delay before the first attempt, doubling with every failed attempt.
+    pub initial_backoff: Duration,
+    /// The maximum delay between two attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+/// How to retry signals and barriers failing to be sent to the sync service,
+/// see [`ClientBuilder::retry`].
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// The number of attempts, including the first one, before giving up.
+    pub max_attempts: u32,
+    /// The delay before the first retry, doubling with every failed attempt.
+    pub initial_backoff: Duration,
+    /// The maximum delay between two attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            run_parameters: None,
+            sync_service: None,
+            handshake_timeout: Duration::from_secs(30),
+            run_out_rotation: None,
+            write_summary: false,
+            chunk_threshold: None,
+            reconnect: None,
+            keepalive: Some(Duration::from_secs(30)),
+            retry: None,
+            command_buffer: 64,
+        }
+    }
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses the given runtime parameters instead of parsing them from the
+    /// environment.
+    pub fn run_parameters(mut self, run_parameters: RunParameters) -> Self {
+        self.run_parameters = Some(run_parameters);
+        self
+    }
+
+    /// Sets the maximum duration for connecting to the sync service, including
+    /// the websocket handshake, after which [`ClientBuilder::build`] fails with
+    /// [`InitError::HandshakeTimeout`].
+    ///
+    /// Defaults to 30 seconds.
+    pub fn handshake_timeout(mut self, handshake_timeout: Duration) -> Self {
+        self.handshake_timeout = handshake_timeout;
+        self
+    }
+
+    /// Rotates `run.out` once it exceeds `max_size` bytes, keeping up to
+    /// `max_files` rotated files, i.e. `run.out.1` (the most recent) to
+    /// `run.out.<max_files>`.
+    ///
+    /// Disabled by default.
+    pub fn rotate_run_out(mut self, max_size: u64, max_files: usize) -> Self {
+        self.run_out_rotation = Some(Rotation {
+            max_size,
+            max_files,
+        });
+        self
+    }
+
+    /// Writes a `summary.json` to the outputs directory once the outcome of the
+    /// test instance is recorded, see [`Client::set_summary`].
+    ///
+    /// Whatever the outcome, `summary.json` is written before the outcome
+    /// event of `run.out`.
+    ///
+    /// Disabled by default.
+    pub fn write_summary(mut self, write_summary: bool) -> Self {
+        self.write_summary = write_summary;
+        self
+    }
+
+    /// Splits published payloads whose JSON encoding exceeds `threshold` bytes
+    /// into ordered fragments, e.g. to stay below the frame limit of the sync
+    /// service. Subscriptions reassemble fragments irrespective of this
+    /// setting.
+    ///
+    /// Note that other SDKs don't reassemble the fragments, thus subscribers
+    /// of chunked topics must use this SDK. Disabled by default.
+    pub fn chunk_payloads(mut self, threshold: usize) -> Self {
+        self.chunk_threshold = Some(threshold);
+        self
+    }
+
+    /// Reconnects to the sync service according to `policy` once the
+    /// connection is lost, e.g. for long running soak tests.
+    ///
+    /// Requests pending while the connection is lost, including
+    /// subscriptions, fail with [`Error::Disconnected`], as the sync service
+    /// may or may not have processed them. Later requests use the new
+    /// connection.
+    ///
+    /// The global and group sequence numbers claimed while building the
+    /// client are kept, i.e. never claimed again on the new connection, thus
+    /// leaders stay the same.
+    ///
+    /// Disabled by default, as reconnecting may mask real failures, in which
+    /// case the [`Client`] stops working once the connection is lost.
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    /// Pings the sync service every `interval`, so that intermediaries don't
+    /// reap the connection of an instance idling on e.g. a long barrier, or
+    /// never if `None`.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn keepalive(mut self, interval: Option<Duration>) -> Self {
+        self.keepalive = interval;
+        self
+    }
+
+    /// Retries signals and barriers according to `policy` when they fail to be
+    /// sent to the sync service, e.g. while the connection is briefly broken.
+    ///
+    /// Only requests that weren't written to the connection at all are
+    /// retried, once it was re-established, i.e. [`Error::NotSent`], as
+    /// signalling a state twice would count twice. Thus retrying requires
+    /// [`ClientBuilder::reconnect`]. Errors returned by the sync service, i.e.
+    /// [`Error::SyncService`], are never retried.
+    ///
+    /// Disabled by default.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Sets the number of requests of the [`Client`] and its clones queued
+    /// for the background task before further requests wait, e.g. raised for
+    /// plans recording many metrics or signals concurrently.
+    ///
+    /// Defaults to 64.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn command_buffer(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "command buffer capacity must be positive");
+        self.command_buffer = capacity;
+        self
+    }
+
+    /// Connects to the sync service at `host` and `port`, e.g. for custom
+    /// deployments or a local sync service.
+    ///
+    /// Defaults to `SYNC_SERVICE_HOST` and `SYNC_SERVICE_PORT`, see
+    /// [`RunParameters::sync_service_addr`].
+    pub fn sync_service_addr(mut self, host: impl Into<String>, port: u16) -> Self {
+        self.sync_service = Some((host.into(), port));
+        self
+    }
+
+    /// Connects to the sync service, waits for the network to initialize and
+    /// claims the global and group sequence numbers, waiting for other
+    /// instances to do the same.
+    pub async fn build(self) -> Result<Client, Box<dyn std::error::Error>> {
+        let run_parameters = match self.run_parameters.clone() {
+            Some(run_parameters) => run_parameters,
+            None => RunParameters::try_parse()?,
+        };
+
+        let (cmd_tx, cmd_rx) = channel(self.command_buffer);
+
+        let sync_service = self
+            .sync_service
+            .clone()
+            .unwrap_or_else(|| run_parameters.sync_service_addr());
+
+        let background = if run_parameters.testground_no_sync {
+            BackgroundTask::local(cmd_rx, run_parameters.clone()).await?
+        } else {
+            let (host, port) = &sync_service;
+            BackgroundTask::new(
+                (host.as_str(), *port),
+                self.handshake_timeout,
+                cmd_rx,
+                run_parameters.clone(),
+            )
+            .await?
+        };
+
+        self.build_with(background, cmd_tx, run_parameters, sync_service)
+            .await
+    }
+
+    /// Builds a [`Client`] on top of a sync service connected through `socket`,
+    /// e.g. failing on demand.
+    #[cfg(test)]
+    pub(crate) async fn build_with_socket(
+        self,
+        socket: Box<dyn crate::background::Socket>,
+    ) -> Result<Client, Box<dyn std::error::Error>> {
+        let run_parameters = self.run_parameters.clone().expect("RunParameters");
+        let sync_service = self.sync_service.clone().expect("Sync service");
+
+        let (cmd_tx, cmd_rx) = channel(self.command_buffer);
 
-        let (cmd_tx, cmd_rx) = channel(1);
+        let background =
+            BackgroundTask::with_socket(socket, cmd_rx, run_parameters.clone()).await?;
 
-        let background = BackgroundTask::new(cmd_rx, run_parameters.clone()).await?;
+        self.build_with(background, cmd_tx, run_parameters, sync_service)
+            .await
+    }
+
+    async fn build_with(
+        self,
+        mut background: BackgroundTask,
+        cmd_tx: Sender<Command>,
+        run_parameters: RunParameters,
+        sync_service: (String, u16),
+    ) -> Result<Client, Box<dyn std::error::Error>> {
+        if let Some(interval) = self.keepalive {
+            background = background.keepalive(interval);
+        }
+
+        // There is nothing to reconnect to with an in-process sync service.
+        if let Some(policy) = self
+            .reconnect
+            .filter(|_| !run_parameters.testground_no_sync)
+        {
+            background = background.reconnect(sync_service, self.handshake_timeout, policy);
+        }
 
         let run_out = run_parameters
-            .test_outputs_path
-            .to_str()
-            .map(|path_str| {
-                if path_str.is_empty() {
-                    None
-                } else {
-                    let mut path = PathBuf::from(path_str);
-                    path.push("run.out");
-                    Some(path)
-                }
-            })
-            .unwrap_or(None);
+            .output_path_if_set("run.out")
+            .map(|path| Arc::new(RunOut::new(path, self.run_out_rotation)));
 
         // `global_seq` and `group_seq` are initialized by 0 at this point since no way to signal to the sync service.
-        let mut client = Self {
+        let mut client = Client {
             cmd_tx,
+            blocking_tx: background.blocking_sender(),
             run_parameters,
             global_seq: 0,
             group_seq: 0,
             run_out,
+            write_summary: self.write_summary,
+            stats: Default::default(),
+            chunker: self
+                .chunk_threshold
+                .map(|threshold| Arc::new(Chunker::new(threshold))),
+            runtime: None,
+            retry: self.retry,
+            network_changes: Default::default(),
+            outcome_recorded: Default::default(),
+            panics: Default::default(),
         };
 
         tokio::spawn(background.run());
@@ -78,16 +395,13 @@ impl Client {
             // Note that the sdk-go only signals, but not waits.
             .signal_and_wait(
                 "initialized_global",
-                client.run_parameters.test_instance_count,
+                client.run_parameters.global_instance_count(),
             )
             .await?;
 
         let group_seq_num = client
             // Note that the sdk-go only signals, but not waits.
-            .signal_and_wait(
-                format!("initialized_group_{}", client.run_parameters.test_group_id),
-                client.run_parameters.test_group_instance_count,
-            )
+            .signal_and_wait_group("initialized_group")
             .await?;
 
         client.record_message(format!(
@@ -98,40 +412,164 @@ impl Client {
         client.global_seq = global_seq_num;
         client.group_seq = group_seq_num;
 
+        let cmd = Command::SetInstanceSeq {
+            seq: global_seq_num,
+        };
+        client
+            .cmd_tx
+            .send(cmd)
+            .await
+            .map_err(|_| Error::Disconnected)?;
+
+        let global_tags = HashMap::from([
+            ("run_id".to_owned(), client.run_parameters.test_run.clone()),
+            (
+                "group_id".to_owned(),
+                client.run_parameters.test_group_id.clone(),
+            ),
+            ("instance_seq".to_owned(), global_seq_num.to_string()),
+        ]);
+        client.set_global_tags(global_tags).await;
+
+        Ok(client)
+    }
+
+    /// Like [`ClientBuilder::build`], but blocks on the initialization, for
+    /// synchronous test setups, see [`Client::new_and_init_blocking`].
+    pub fn build_blocking(self) -> Result<Client, Box<dyn std::error::Error>> {
+        // Blocking within a runtime would panic.
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return Err(InitError::NestedRuntime.into());
+        }
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+
+        let mut client = runtime.block_on(self.build())?;
+        client.runtime = Some(Arc::new(OwnedRuntime(Some(runtime))));
+
         Ok(client)
     }
+}
+
+impl Client {
+    pub async fn new_and_init() -> Result<Self, Box<dyn std::error::Error>> {
+        ClientBuilder::new().build().await
+    }
+
+    /// ```new_and_init_blocking``` initializes a client like
+    /// [`Client::new_and_init`], but without an async runtime, for synchronous
+    /// test harnesses. The client owns a runtime driving its background task,
+    /// on which [`Client::block_on`] runs the client's futures.
+    ///
+    /// Fails with [`InitError::NestedRuntime`] if called from within an async
+    /// runtime, e.g. `#[tokio::main]`, use [`Client::new_and_init`] there.
+    ///
+    /// Dropping the last clone of the client shuts the runtime down without
+    /// waiting for its tasks, thus the last clone may be dropped anywhere,
+    /// including within a task of the runtime. Futures still running on the
+    /// runtime, e.g. spawned by the plan, are dropped.
+    pub fn new_and_init_blocking() -> Result<Self, Box<dyn std::error::Error>> {
+        ClientBuilder::new().build_blocking()
+    }
+
+    /// Runs `future`, e.g. `client.signal_and_wait_all("ready")`, to
+    /// completion on the runtime of a client created by
+    /// [`Client::new_and_init_blocking`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the client doesn't own a runtime, or if called from within an
+    /// async runtime.
+    pub fn block_on<F: std::future::Future>(&self, future: F) -> F::Output {
+        self.runtime
+            .as_ref()
+            .expect("Client without a runtime, see `Client::new_and_init_blocking`")
+            .0
+            .as_ref()
+            .expect("Runtime")
+            .block_on(future)
+    }
 
     /// ```publish``` publishes an item on the supplied topic.
     ///
     /// Once the item has been published successfully,
     /// returning the sequence number of the new item in the ordered topic,
     /// or an error if one occurred, starting with 1 (for the first item).
+    ///
+    /// If the payload is split into fragments, see
+    /// [`ClientBuilder::chunk_payloads`], the sequence number of the last
+    /// fragment is returned.
+    ///
+    /// The sync service acknowledges an item once it is stored in the topic,
+    /// thus once `publish` returns `N`, every subscription to the topic,
+    /// whether started before or after, yields the item as its `N`-th
+    /// element, see [`Client::subscribe_indexed`]. No further flush is
+    /// needed.
     pub async fn publish(
         &self,
         topic: impl Into<Cow<'static, str>>,
         message: impl Into<Cow<'static, serde_json::Value>>,
     ) -> Result<u64, Error> {
-        let (sender, receiver) = oneshot::channel();
+        let topic = topic.into().into_owned();
 
-        let cmd = Command::Publish {
-            topic: topic.into().into_owned(),
-            message: message.into().into_owned(),
-            sender,
-        };
+        let mut seq = 0;
+        for message in self.fragments(message.into().into_owned()) {
+            let (sender, receiver) = oneshot::channel();
+
+            let cmd = Command::Publish {
+                topic: topic.clone(),
+                message,
+                sender,
+            };
 
-        self.cmd_tx.send(cmd).await.expect(BACKGROUND_RECEIVER);
+            seq = self.request(cmd, receiver).await?;
+        }
+
+        Ok(seq)
+    }
+
+    /// ```publish_typed``` publishes `value`, serialized to JSON, on the
+    /// supplied topic, see [`Client::publish`].
+    ///
+    /// Fails with [`Error::Serialize`] if `value` can't be serialized.
+    pub async fn publish_typed<T: Serialize>(
+        &self,
+        topic: impl Into<Cow<'static, str>>,
+        value: &T,
+    ) -> Result<u64, Error> {
+        let message = serde_json::to_value(value).map_err(Error::Serialize)?;
 
-        receiver.await.expect(BACKGROUND_SENDER)
+        self.publish(topic, Cow::Owned(message)).await
+    }
+
+    /// ```publish_bytes``` publishes a binary payload, e.g. a serialized
+    /// protobuf, on the supplied topic, see [`Client::publish`].
+    ///
+    /// As payloads are JSON, the bytes are published as a base64 encoded
+    /// string, like the Go SDK publishes a `[]byte`.
+    pub async fn publish_bytes(
+        &self,
+        topic: impl Into<Cow<'static, str>>,
+        bytes: impl AsRef<[u8]>,
+    ) -> Result<u64, Error> {
+        let message = serde_json::Value::String(BASE64.encode(bytes));
+
+        self.publish(topic, Cow::Owned(message)).await
     }
 
     /// ```subscribe``` subscribes to a topic, consuming ordered, elements from
-    /// index 0.
+    /// index 0, see [`Client::subscribe_after`] for skipping elements.
+    ///
+    /// Dropping the returned [`Stream`] cancels the subscription.
     ///
-    /// Note that once the capacity of the returned [`Stream`] is reached, the
-    /// background task blocks and thus all work related to the [`Client`] will
-    /// pause until elements from the [`Stream`] are consumed and thus capacity
-    /// is freed. Callers of [`Client::subscribe`] should either set a high
-    /// capacity, continuously read from the returned [`Stream`] or drop it.
+    /// Note that once the capacity of the returned [`Stream`] is reached,
+    /// further elements are buffered in memory until elements from the
+    /// [`Stream`] are consumed and thus capacity is freed. Other work related
+    /// to the [`Client`], e.g. a barrier awaited before consuming a burst of
+    /// elements, carries on meanwhile. Callers of [`Client::subscribe`] should
+    /// still continuously read from the returned [`Stream`] or drop it.
     ///
     /// ```no_run
     /// # use testground::client::Client;
@@ -148,144 +586,853 @@ impl Client {
         let cmd = Command::Subscribe {
             topic: topic.into().into_owned(),
             stream,
+            cancel: None,
         };
 
-        self.cmd_tx.send(cmd).await.expect(BACKGROUND_RECEIVER);
+        self.subscription(cmd).await;
 
-        ReceiverStream::new(out)
+        chunking::reassemble(ReceiverStream::new(out))
     }
 
-    /// ```signal_and_wait``` composes SignalEntry and Barrier,
-    /// signalling entry on the supplied state,
-    /// and then awaiting until the required value has been reached.
-    pub async fn signal_and_wait(
+    /// ```subscribe_collect``` subscribes to a topic like [`Client::subscribe`],
+    /// collecting the first `n` elements, or returning the first error
+    /// encountered.
+    ///
+    /// The subscription is cancelled once `n` elements arrived.
+    pub async fn subscribe_collect(
         &self,
-        state: impl Into<Cow<'static, str>>,
-        target: u64,
-    ) -> Result<u64, Error> {
-        let state = state.into().into_owned();
-
-        let res = self.signal(state.clone()).await?;
+        topic: impl Into<Cow<'static, str>>,
+        n: usize,
+    ) -> Result<Vec<serde_json::Value>, Error> {
+        let (mut stream, subscription) = self.subscribe_cancellable(topic, n.max(1)).await;
+
+        let mut items = Vec::with_capacity(n);
+        while items.len() < n {
+            match stream.next().await {
+                Some(item) => items.push(item?),
+                // The background task is gone.
+                None => return Err(Error::Disconnected),
+            }
+        }
 
-        self.barrier(state, target).await?;
+        subscription.cancel();
 
-        Ok(res)
+        Ok(items)
     }
 
-    /// ```signal``` increments the state counter by one,
-    /// returning the value of the new value of the counter,
-    /// or an error if the operation fails.
-    pub async fn signal(&self, state: impl Into<Cow<'static, str>>) -> Result<u64, Error> {
-        let (sender, receiver) = oneshot::channel();
+    /// ```subscribe_cancellable``` subscribes to a topic like
+    /// [`Client::subscribe`], along with a [`Subscription`] handle to cancel
+    /// the subscription, sparing the sync service from delivering elements no
+    /// one is interested in anymore.
+    pub async fn subscribe_cancellable(
+        &self,
+        topic: impl Into<Cow<'static, str>>,
+        capacity: usize,
+    ) -> (
+        impl Stream<Item = Result<serde_json::Value, Error>>,
+        Subscription,
+    ) {
+        let (stream, out) = mpsc::channel(capacity);
+        let (cancel, cancelled) = oneshot::channel();
 
-        let state = state.into().into_owned();
-        let cmd = Command::SignalEntry { state, sender };
+        let cmd = Command::Subscribe {
+            topic: topic.into().into_owned(),
+            stream,
+            cancel: Some(cancelled),
+        };
 
-        self.cmd_tx.send(cmd).await.expect(BACKGROUND_RECEIVER);
+        self.subscription(cmd).await;
 
-        receiver.await.expect(BACKGROUND_SENDER)
+        (
+            chunking::reassemble(ReceiverStream::new(out)),
+            Subscription::new(cancel),
+        )
     }
 
-    /// ```barrier``` sets a barrier on the supplied ```state``` that fires when it reaches its target value (or higher).
-    pub async fn barrier(
+    /// ```publish_and_wait_for``` publishes `message` on `topic`, then waits
+    /// for the topic to hold `count` elements, returning them in topic order,
+    /// e.g. for all instances to share their addresses.
+    ///
+    /// As subscriptions deliver from index 0, the returned elements include
+    /// the one published by this instance exactly once, and all instances
+    /// observe the same order. The subscription is cancelled once `count`
+    /// elements arrived.
+    pub async fn publish_and_wait_for(
         &self,
-        state: impl Into<Cow<'static, str>>,
-        target: u64,
-    ) -> Result<(), Error> {
-        let (sender, receiver) = oneshot::channel();
-
-        let state = state.into().into_owned();
-        let cmd = Command::Barrier {
-            state,
-            target,
-            sender,
-        };
+        topic: impl Into<Cow<'static, str>>,
+        message: impl Into<Cow<'static, serde_json::Value>>,
+        count: usize,
+    ) -> Result<Vec<serde_json::Value>, Error> {
+        let topic = topic.into();
 
-        self.cmd_tx.send(cmd).await.expect(BACKGROUND_RECEIVER);
+        self.publish(topic.clone(), message).await?;
 
-        receiver.await.expect(BACKGROUND_SENDER)
+        self.subscribe_collect(topic, count).await
     }
 
-    /// ```wait_network_initialized``` waits for the sidecar to initialize the network,
-    /// if the sidecar is enabled.
-    async fn wait_network_initialized(&self) -> Result<(), Error> {
-        // Event
-        let (sender, receiver) = oneshot::channel();
-
-        let cmd = Command::WaitNetworkInitializedStart { sender };
-
-        self.cmd_tx.send(cmd).await.expect(BACKGROUND_RECEIVER);
+    /// ```group_publish``` publishes an item on a topic private to the group
+    /// of this instance, see [`Client::group_subscribe`].
+    ///
+    /// Unlike topics of [`Client::publish`], which are shared by all groups of
+    /// the run, group topics are additionally scoped by `test_group_id`, thus
+    /// the same topic name refers to a distinct topic in each group.
+    pub async fn group_publish(
+        &self,
+        topic: impl Into<Cow<'static, str>>,
+        message: impl Into<Cow<'static, serde_json::Value>>,
+    ) -> Result<u64, Error> {
+        let topic = topic.into().into_owned();
 
-        receiver.await.expect(BACKGROUND_SENDER)?;
+        let mut seq = 0;
+        for message in self.fragments(message.into().into_owned()) {
+            let (sender, receiver) = oneshot::channel();
 
-        // Barrier
-        let (sender, receiver) = oneshot::channel();
+            let cmd = Command::GroupPublish {
+                topic: topic.clone(),
+                message,
+                sender,
+            };
 
-        let cmd = Command::WaitNetworkInitializedBarrier { sender };
+            seq = self.request(cmd, receiver).await?;
+        }
 
-        self.cmd_tx.send(cmd).await.expect(BACKGROUND_RECEIVER);
+        Ok(seq)
+    }
 
-        receiver.await.expect(BACKGROUND_SENDER)?;
+    /// ```group_subscribe``` subscribes to a topic private to the group of
+    /// this instance, only receiving items published with
+    /// [`Client::group_publish`] by instances of the same group.
+    ///
+    /// See [`Client::subscribe`] regarding the `capacity` of the stream.
+    pub async fn group_subscribe(
+        &self,
+        topic: impl Into<Cow<'static, str>>,
+        capacity: usize,
+    ) -> impl Stream<Item = Result<serde_json::Value, Error>> {
+        let (stream, out) = mpsc::channel(capacity);
 
-        // Event
-        let (sender, receiver) = oneshot::channel();
+        let cmd = Command::GroupSubscribe {
+            topic: topic.into().into_owned(),
+            stream,
+        };
 
-        let cmd = Command::WaitNetworkInitializedEnd { sender };
+        self.subscription(cmd).await;
 
-        self.cmd_tx.send(cmd).await.expect(BACKGROUND_RECEIVER);
+        chunking::reassemble(ReceiverStream::new(out))
+    }
 
-        receiver.await.expect(BACKGROUND_SENDER)?;
+    /// ```subscribe_typed``` subscribes to a topic like [`Client::subscribe`],
+    /// deserializing each element into `T`.
+    ///
+    /// Elements failing to deserialize, e.g. an empty payload, are yielded as
+    /// [`Error::Serde`], without closing the stream.
+    pub async fn subscribe_typed<T: DeserializeOwned>(
+        &self,
+        topic: impl Into<Cow<'static, str>>,
+        capacity: usize,
+    ) -> impl Stream<Item = Result<T, Error>> {
+        self.subscribe(topic, capacity)
+            .await
+            .map(|item| Ok(serde_json::from_value(item?)?))
+    }
 
-        Ok(())
+    /// ```subscribe_bytes``` subscribes to a topic like [`Client::subscribe`],
+    /// decoding each element from a base64 encoded string, see
+    /// [`Client::publish_bytes`].
+    ///
+    /// Elements other than strings are yielded as [`Error::Serde`], and
+    /// strings failing to decode as [`Error::Base64`], without closing the
+    /// stream.
+    pub async fn subscribe_bytes(
+        &self,
+        topic: impl Into<Cow<'static, str>>,
+        capacity: usize,
+    ) -> impl Stream<Item = Result<Vec<u8>, Error>> {
+        self.subscribe_typed::<String>(topic, capacity)
+            .await
+            .map(|item| Ok(BASE64.decode(item?)?))
     }
 
-    /// ```configure_network``` asks the sidecar to configure the network.
-    pub async fn configure_network(&self, config: NetworkConfiguration) -> Result<(), Error> {
-        // Publish
-        let (sender, receiver) = oneshot::channel();
+    /// ```subscribe_indexed``` subscribes to a topic like [`Client::subscribe`],
+    /// pairing each element with its sequence number in the topic, starting
+    /// with 1 (for the first item).
+    ///
+    /// If `validate_contiguity` is set, an element following a gap in the
+    /// sequence is replaced by [`Error::SequenceGap`], e.g. to assert that no
+    /// message was lost. See [`subscription::contiguous`].
+    ///
+    /// Note that sequence numbers count reassembled payloads, thus differ from
+    /// those returned by [`Client::publish`] on topics with chunked payloads.
+    pub async fn subscribe_indexed(
+        &self,
+        topic: impl Into<Cow<'static, str>>,
+        capacity: usize,
+        validate_contiguity: bool,
+    ) -> impl Stream<Item = Result<(u64, serde_json::Value), Error>> {
+        let mut seq = 0;
+
+        let stream = self.subscribe(topic, capacity).await.map(move |item| {
+            item.map(|item| {
+                seq += 1;
+                (seq, item)
+            })
+        });
 
-        let state = config.callback_state.clone();
-        let target = if let Some(callback_target) = config.callback_target {
-            callback_target
+        if validate_contiguity {
+            Either::Left(subscription::contiguous(stream))
         } else {
-            0
-        };
-
-        let cmd = Command::NetworkShaping { sender, config };
+            Either::Right(stream)
+        }
+    }
 
-        self.cmd_tx.send(cmd).await.expect(BACKGROUND_RECEIVER);
+    /// ```subscribe_after``` subscribes to a topic like [`Client::subscribe`],
+    /// skipping the first `after` elements, e.g. those published before a late
+    /// joining instance started, as known from the sequence number returned by
+    /// [`Client::publish`].
+    ///
+    /// The sync service has no notion of an offset, always delivering elements
+    /// from index 0, thus skipped elements are still received, but dropped by
+    /// the client. Like [`Client::subscribe_indexed`], reassembled payloads are
+    /// counted, rather than their fragments.
+    pub async fn subscribe_after(
+        &self,
+        topic: impl Into<Cow<'static, str>>,
+        capacity: usize,
+        after: u64,
+    ) -> impl Stream<Item = Result<serde_json::Value, Error>> {
+        let mut skip = after;
 
-        receiver.await.expect(BACKGROUND_SENDER)?;
+        self.subscribe(topic, capacity).await.filter(move |item| {
+            // Errors don't count as elements.
+            if item.is_err() || skip == 0 {
+                return true;
+            }
 
-        self.barrier(state, target).await?;
+            skip -= 1;
+            false
+        })
+    }
 
-        Ok(())
+    /// ```subscribe_filter``` subscribes to a topic like [`Client::subscribe`],
+    /// yielding only the elements matching `predicate`, e.g. those addressed
+    /// to this instance on a busy topic.
+    ///
+    /// Errors of the subscription are passed through unfiltered.
+    pub async fn subscribe_filter(
+        &self,
+        topic: impl Into<Cow<'static, str>>,
+        capacity: usize,
+        predicate: impl Fn(&serde_json::Value) -> bool,
+    ) -> impl Stream<Item = Result<serde_json::Value, Error>> {
+        self.subscribe(topic, capacity)
+            .await
+            .filter(move |item| item.as_ref().map_or(true, &predicate))
     }
 
-    pub fn record_message(&self, message: impl Into<Cow<'static, str>>) {
-        let message = message.into().into_owned();
+    /// ```subscribe_idle_timeout``` subscribes to a topic like
+    /// [`Client::subscribe`], yielding [`Error::IdleTimeout`] whenever no
+    /// element arrived within `idle` of the previous one.
+    ///
+    /// The stream stays open after a timeout, so that the consumer can decide
+    /// whether to keep waiting. See [`subscription::idle_timeout`].
+    pub async fn subscribe_idle_timeout(
+        &self,
+        topic: impl Into<Cow<'static, str>>,
+        capacity: usize,
+        idle: Duration,
+    ) -> impl Stream<Item = Result<serde_json::Value, Error>> {
+        let stream = self.subscribe(topic, capacity).await;
 
-        let event = Event {
-            event: EventType::Message { message },
-        };
+        Box::pin(subscription::idle_timeout(Box::pin(stream), idle))
+    }
 
-        //TODO implement logger similar to go-sdk
+    /// ```await_all_outcomes``` waits for `expected` test instances to record
+    /// their outcome, i.e. success, failure or crash, returning the outcomes in
+    /// the order they were recorded.
+    ///
+    /// Useful for a supervising instance needing the entire run to reach a
+    /// terminal state, e.g. before doing aggregate analysis. Note that the
+    /// outcome of this instance is included, if recorded by a clone of this
+    /// client.
+    ///
+    /// Fails with [`Error::Closed`] if another clone closed the client, and
+    /// with [`Error::Disconnected`] if the connection is lost for good.
+    pub async fn await_all_outcomes(&self, expected: u64) -> Result<Vec<Outcome>, Error> {
+        let (stream, out) = mpsc::channel(u16::MAX.into());
 
-        let json_event = serde_json::to_string(&event).expect("Event Serialization");
+        let cmd = Command::SubscribeEvents { stream };
 
-        println!("{}", json_event);
+        self.cmd_tx.send(cmd).await.map_err(|_| Error::Closed)?;
 
-        self.write(&event.event);
-    }
+        let mut events = ReceiverStream::new(out);
+        let mut outcomes = Vec::new();
 
-    pub async fn record_success(self) -> Result<(), Error> {
-        let (sender, receiver) = oneshot::channel();
+        while (outcomes.len() as u64) < expected {
+            let event = events.next().await.ok_or(Error::Disconnected)??;
 
-        let cmd = Command::SignalSuccess { sender };
+            // Skip non-terminal events, e.g. stage events.
+            if let Ok(outcome) = serde_json::from_value(event) {
+                outcomes.push(outcome);
+            }
+        }
 
-        self.cmd_tx.send(cmd).await.expect(BACKGROUND_RECEIVER);
+        Ok(outcomes)
+    }
 
-        receiver.await.expect(BACKGROUND_SENDER)?;
+    /// ```signal_and_wait``` composes SignalEntry and Barrier,
+    /// signalling entry on the supplied state,
+    /// and then awaiting until the required value has been reached.
+    pub async fn signal_and_wait(
+        &self,
+        state: impl Into<Cow<'static, str>>,
+        target: u64,
+    ) -> Result<u64, Error> {
+        let state = state.into().into_owned();
+
+        let res = self.signal_entry(state.clone()).await?;
+
+        self.barrier(state, target).await?;
+
+        Ok(res)
+    }
+
+    /// ```signal_and_check``` signals entry on ```state``` like
+    /// [`Client::signal_entry`], returning the sequence number along with
+    /// whether it reached ```target```, without waiting for other instances.
+    ///
+    /// Exactly one instance gets the sequence number equal to ```target```,
+    /// i.e. arrives last, e.g. to publish an aggregate result once all other
+    /// instances signalled. Instances signalling beyond the target get
+    /// `false`.
+    pub async fn signal_and_check(
+        &self,
+        state: impl Into<Cow<'static, str>>,
+        target: u64,
+    ) -> Result<(u64, bool), Error> {
+        let seq = self.signal_entry(state).await?;
+
+        Ok((seq, seq == target))
+    }
+
+    /// ```signal_and_wait_timeout``` composes SignalEntry and Barrier like
+    /// [`Client::signal_and_wait`], failing with [`Error::Timeout`] if the
+    /// state doesn't reach its target within `timeout`.
+    ///
+    /// Only the barrier is subject to the timeout, i.e. the entry is always
+    /// registered before the timeout starts, and the barrier is cancelled with
+    /// the sync service on timeout, see [`Client::barrier_with_timeout`].
+    pub async fn signal_and_wait_timeout(
+        &self,
+        state: impl Into<Cow<'static, str>>,
+        target: u64,
+        timeout: Duration,
+    ) -> Result<u64, Error> {
+        let state = state.into().into_owned();
+
+        let res = self.signal_entry(state.clone()).await?;
+
+        self.barrier_with_timeout(state, target, timeout).await?;
+
+        Ok(res)
+    }
+
+    /// ```group_signal_and_wait``` composes SignalEntry and Barrier like
+    /// [`Client::signal_and_wait_group`].
+    #[deprecated(since = "0.5.0", note = "Use `Client::signal_and_wait_group` instead.")]
+    pub async fn group_signal_and_wait(
+        &self,
+        state: impl Into<Cow<'static, str>>,
+    ) -> Result<u64, Error> {
+        self.signal_and_wait_group(state).await
+    }
+
+    /// ```signal_and_wait_group``` composes SignalEntry and Barrier like
+    /// [`Client::signal_and_wait`], scoped to the group of this instance.
+    ///
+    /// The state is suffixed with the group ID, e.g. `ready_single`, so that
+    /// groups don't interfere with each other, and the target is the number of
+    /// instances in the group. Returns the group-scoped sequence number.
+    pub async fn signal_and_wait_group(
+        &self,
+        state: impl Into<Cow<'static, str>>,
+    ) -> Result<u64, Error> {
+        self.signal_and_wait(
+            self.group_state(state),
+            self.run_parameters.group_instance_count(),
+        )
+        .await
+    }
+
+    /// ```signal_and_wait_all``` composes SignalEntry and Barrier like
+    /// [`Client::signal_and_wait`], waiting for all instances of the run, i.e.
+    /// `test_instance_count`, to signal the state.
+    pub async fn signal_and_wait_all(
+        &self,
+        state: impl Into<Cow<'static, str>>,
+    ) -> Result<u64, Error> {
+        self.signal_and_wait(state, self.run_parameters.global_instance_count())
+            .await
+    }
+
+    /// ```signal``` increments the state counter by one like
+    /// [`Client::signal_entry`].
+    #[deprecated(since = "0.5.0", note = "Use `Client::signal_entry` instead.")]
+    pub async fn signal(&self, state: impl Into<Cow<'static, str>>) -> Result<u64, Error> {
+        self.signal_entry(state).await
+    }
+
+    /// ```signal_entry``` increments the state counter by one,
+    /// returning the value of the new value of the counter,
+    /// or an error if the operation fails.
+    ///
+    /// Unlike [`Client::signal_and_wait`], it doesn't wait for the counter to
+    /// reach a target, see [`Client::barrier`] for that. Matches `SignalEntry`
+    /// of the Go SDK.
+    pub async fn signal_entry(&self, state: impl Into<Cow<'static, str>>) -> Result<u64, Error> {
+        let state = state.into().into_owned();
+
+        self.retry(|| async {
+            let (sender, receiver) = oneshot::channel();
+
+            let cmd = Command::SignalEntry {
+                state: state.clone(),
+                sender,
+            };
+
+            self.request(cmd, receiver).await
+        })
+        .await
+    }
+
+    /// Retries `request` according to the [`RetryPolicy`], if any, as long
+    /// as it isn't sent, i.e. fails with [`Error::NotSent`].
+    async fn retry<T, R>(&self, mut request: impl FnMut() -> R) -> Result<T, Error>
+    where
+        R: std::future::Future<Output = Result<T, Error>>,
+    {
+        let policy = match self.retry {
+            Some(policy) => policy,
+            None => return request().await,
+        };
+
+        let mut backoff = policy.initial_backoff;
+
+        for _ in 1..policy.max_attempts {
+            match request().await {
+                Err(Error::NotSent(e)) => {
+                    log::warn!("Retrying request not sent: {}", e);
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(policy.max_backoff);
+                }
+                res => return res,
+            }
+        }
+
+        request().await
+    }
+
+    /// ```raw_request``` sends `request`, a JSON object such as
+    /// `{"signal_entry": {"state": "..."}}`, to the sync service as is, and
+    /// returns the response as is, e.g. for requests not supported by this
+    /// SDK yet.
+    ///
+    /// The `id` and `is_cancel` fields of the request are set by the client.
+    /// Unlike other requests, states and topics aren't contextualized with the
+    /// run, and an error of the sync service is returned within the `error`
+    /// field of the response. Only the first response is returned, thus
+    /// subscriptions aren't supported.
+    pub async fn raw_request(
+        &self,
+        request: serde_json::Value,
+    ) -> Result<serde_json::Value, Error> {
+        let (sender, receiver) = oneshot::channel();
+
+        let cmd = Command::Raw { request, sender };
+
+        self.request(cmd, receiver).await
+    }
+
+    /// ```state_count``` returns the current value of the state counter, i.e.
+    /// the number of times ```state``` has been signalled.
+    ///
+    /// Not supported by the Testground sync service, thus only available with
+    /// the `testing` feature, for plans tested against
+    /// [`MockSyncService`](crate::testing::MockSyncService) or run with
+    /// `TESTGROUND_NO_SYNC`.
+    #[cfg(any(test, feature = "testing"))]
+    pub async fn state_count(&self, state: impl Into<Cow<'static, str>>) -> Result<u64, Error> {
+        let (sender, receiver) = oneshot::channel();
+
+        let state = state.into().into_owned();
+        let cmd = Command::StateCount { state, sender };
+
+        self.request(cmd, receiver).await
+    }
+
+    /// ```signal_and_snapshot``` signals entry on ```state``` and then reads the
+    /// current counters of the ```others``` states, e.g. to debug race
+    /// conditions between related states.
+    ///
+    /// Returns the sequence number of the signal along with the counters.
+    /// Note that the counters are read right after signalling, yet other
+    /// instances may signal in between.
+    ///
+    /// Not supported by the Testground sync service, see
+    /// [`Client::state_count`].
+    #[cfg(any(test, feature = "testing"))]
+    pub async fn signal_and_snapshot(
+        &self,
+        state: impl Into<Cow<'static, str>>,
+        others: &[String],
+    ) -> Result<(u64, HashMap<String, u64>), Error> {
+        let seq = self.signal_entry(state).await?;
+
+        let counts = futures::future::try_join_all(
+            others.iter().map(|other| self.state_count(other.clone())),
+        )
+        .await?;
+
+        Ok((seq, others.iter().cloned().zip(counts).collect()))
+    }
+
+    /// ```barrier``` sets a barrier on the supplied ```state``` that fires when it reaches its target value (or higher).
+    ///
+    /// A target exceeding [`RunParameters::global_instance_count`] is logged as
+    /// a warning, as it is never reached if each instance signals once. See
+    /// [`Client::barrier_all`] and [`Client::barrier_group`] for the usual
+    /// targets.
+    ///
+    /// Barriers awaited concurrently, e.g. with `join!` or by clones of this
+    /// client, are pending at the same time and fire independently, in
+    /// whichever order their states reach their targets.
+    pub async fn barrier(
+        &self,
+        state: impl Into<Cow<'static, str>>,
+        target: u64,
+    ) -> Result<(), Error> {
+        let state = state.into().into_owned();
+
+        self.retry(|| async {
+            let (sender, receiver) = oneshot::channel();
+
+            let cmd = Command::Barrier {
+                state: state.clone(),
+                target,
+                sender,
+            };
+
+            self.request(cmd, receiver).await
+        })
+        .await
+    }
+
+    /// ```ping``` checks that the connection to the sync service is alive by
+    /// awaiting the pong answering a websocket ping, like the keepalive of
+    /// [`ClientBuilder::keepalive`], thus leaving no state behind on the sync
+    /// service.
+    ///
+    /// Fails with [`Error::Disconnected`] once the connection is lost, e.g.
+    /// to fail a long running test fast rather than hanging on the next
+    /// barrier, and with [`Error::Closed`] once the background task shut
+    /// down. Combine with [`tokio::time::timeout`] to detect an unresponsive
+    /// sync service.
+    pub async fn ping(&self) -> Result<(), Error> {
+        let (sender, receiver) = oneshot::channel();
+
+        let cmd = Command::Ping { sender };
+
+        self.request(cmd, receiver).await
+    }
+
+    /// ```barrier_all``` sets a barrier like [`Client::barrier`], firing once
+    /// all instances of the run, i.e. `test_instance_count`, signalled the
+    /// state.
+    pub async fn barrier_all(&self, state: impl Into<Cow<'static, str>>) -> Result<(), Error> {
+        self.barrier(state, self.run_parameters.global_instance_count())
+            .await
+    }
+
+    /// ```barrier_group``` sets a barrier like [`Client::barrier`] on the
+    /// group-scoped state of [`Client::signal_and_wait_group`], firing once all
+    /// instances of the group signalled it.
+    pub async fn barrier_group(&self, state: impl Into<Cow<'static, str>>) -> Result<(), Error> {
+        self.barrier(
+            self.group_state(state),
+            self.run_parameters.group_instance_count(),
+        )
+        .await
+    }
+
+    /// ```barrier_with_timeout``` sets a barrier like [`Client::barrier`],
+    /// failing with [`Error::Timeout`] if the state doesn't reach its target
+    /// within `timeout`, e.g. because a peer crashed during setup.
+    ///
+    /// On timeout, the barrier is cancelled with the sync service.
+    pub async fn barrier_with_timeout(
+        &self,
+        state: impl Into<Cow<'static, str>>,
+        target: u64,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let state = state.into();
+
+        // Dropping the barrier on timeout cancels it.
+        match tokio::time::timeout(timeout, self.barrier(state.clone(), target)).await {
+            Ok(res) => res,
+            Err(_) => Err(Error::Timeout {
+                state: state.into_owned(),
+                target,
+                waited: timeout,
+            }),
+        }
+    }
+
+    /// ```barrier_reporting``` sets a barrier like [`Client::barrier`],
+    /// recording a message every `interval` until it fires, e.g. to tell a
+    /// long barrier apart from a stuck instance in the logs.
+    ///
+    /// The sync service only responds to a barrier once it fires, without
+    /// reporting how many instances signalled the state in the meantime, thus
+    /// the messages only report how long the barrier has been waiting.
+    pub async fn barrier_reporting(
+        &self,
+        state: impl Into<Cow<'static, str>>,
+        target: u64,
+        interval: Duration,
+    ) -> Result<(), Error> {
+        let state = state.into();
+
+        let barrier = self.barrier(state.clone(), target);
+        tokio::pin!(barrier);
+
+        let start = tokio::time::Instant::now();
+        let mut ticks = tokio::time::interval_at(start + interval, interval);
+
+        loop {
+            tokio::select! {
+                res = &mut barrier => return res,
+                _ = ticks.tick() => self.record_message(format!(
+                    "Waiting {:?} for {} to reach {}",
+                    start.elapsed(),
+                    state,
+                    target
+                )),
+            }
+        }
+    }
+
+    /// ```barrier_any``` sets a barrier on each of the supplied ```states```,
+    /// firing as soon as any of them reaches its target value (or higher).
+    ///
+    /// Returns the state that fired first, cancelling the remaining barriers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if ```states``` is empty.
+    pub async fn barrier_any(&self, states: Vec<(String, u64)>) -> Result<String, Error> {
+        assert!(
+            !states.is_empty(),
+            "barrier_any requires at least one state"
+        );
+
+        let barriers = states.into_iter().map(|(state, target)| {
+            Box::pin(async move {
+                self.barrier(state.clone(), target).await?;
+                Ok(state)
+            })
+        });
+
+        // Dropping the remaining barriers cancels them.
+        let (fired, _, _) = futures::future::select_all(barriers).await;
+
+        fired
+    }
+
+    /// ```wait_network_initialized``` waits for the sidecar to initialize the network,
+    /// if the sidecar is enabled.
+    async fn wait_network_initialized(&self) -> Result<(), Error> {
+        self.stage_start("network-initialized").await?;
+
+        // Barrier
+        let (sender, receiver) = oneshot::channel();
+
+        let cmd = Command::WaitNetworkInitializedBarrier { sender };
+
+        self.request(cmd, receiver).await?;
+
+        self.stage_end("network-initialized").await?;
+
+        Ok(())
+    }
+
+    /// ```stage_start``` publishes the start of the stage `name` of this
+    /// instance's group, e.g. `warmup` or `measurement`, annotating the
+    /// timeline of the run like the built-in `network-initialized` stage.
+    ///
+    /// Returns the sequence number of the event in the events topic of the
+    /// run.
+    pub async fn stage_start(&self, name: impl Into<Cow<'static, str>>) -> Result<u64, Error> {
+        let (sender, receiver) = oneshot::channel();
+
+        let cmd = Command::StageStart {
+            name: name.into().into_owned(),
+            sender,
+        };
+
+        self.request(cmd, receiver).await
+    }
+
+    /// ```stage_end``` publishes the end of the stage `name`, see
+    /// [`Client::stage_start`].
+    pub async fn stage_end(&self, name: impl Into<Cow<'static, str>>) -> Result<u64, Error> {
+        let (sender, receiver) = oneshot::channel();
+
+        let cmd = Command::StageEnd {
+            name: name.into().into_owned(),
+            sender,
+        };
+
+        self.request(cmd, receiver).await
+    }
+
+    /// ```configure_network``` asks the sidecar to configure the network.
+    ///
+    /// Once the sidecar applied the configuration, i.e. signalled the callback
+    /// state, returns the sequence number of the configuration in the network
+    /// topic of this instance, starting with 1 (for the first configuration).
+    ///
+    /// Fails with [`Error::UnsupportedOnRunner`] on the `local:exec` runner,
+    /// which runs without a sidecar.
+    ///
+    /// Configurations of different networks, see
+    /// [`NetworkConfiguration::network`], share the sequence numbers of this
+    /// instance but are otherwise independent, as long as each uses a
+    /// callback state of its own. On reusing a callback state, the barrier of
+    /// a later configuration fires on the signals of the earlier one, i.e.
+    /// possibly before the sidecar applied the later configuration.
+    pub async fn configure_network(&self, config: NetworkConfiguration) -> Result<u64, Error> {
+        // Publish
+        let (sender, receiver) = oneshot::channel();
+
+        let state = config.callback_state.clone();
+        let target = config.callback_target.unwrap_or_default();
+
+        let cmd = Command::NetworkShaping { sender, config };
+
+        let seq = self.request(cmd, receiver).await?;
+
+        self.barrier(state, target).await?;
+
+        Ok(seq)
+    }
+
+    /// ```disconnect_network``` partitions this instance from the data
+    /// network, disabling its network device and dropping all inbound
+    /// traffic, until [`Client::reconnect_network`].
+    ///
+    /// The callback state is signalled by the sidecar over the control
+    /// network, thus unaffected by the partition. As no other instance
+    /// signals it, the configuration uses a callback state private to this
+    /// instance with a callback target of 1, i.e. returns once the sidecar of
+    /// this instance applied it. Other instances have to be told about the
+    /// partition by other means, e.g. a state signalled beforehand.
+    pub async fn disconnect_network(&self) -> Result<u64, Error> {
+        let config = NetworkConfigurationBuilder::new(self.private_callback_state())
+            .enable(false)
+            .default_link_shape(LinkShapeBuilder::new().filter(FilterAction::Drop).build())
+            .callback_target(1)
+            .build();
+
+        self.configure_network(config).await
+    }
+
+    /// ```reconnect_network``` re-enables the network device of this instance
+    /// after [`Client::disconnect_network`], shaping its traffic with
+    /// `link_shape`. The routing policy is reset to allow all routing.
+    ///
+    /// Like [`Client::disconnect_network`], returns once the sidecar of this
+    /// instance applied the configuration.
+    pub async fn reconnect_network(&self, link_shape: LinkShape) -> Result<u64, Error> {
+        let config = NetworkConfigurationBuilder::new(self.private_callback_state())
+            .default_link_shape(link_shape)
+            .callback_target(1)
+            .build();
+
+        self.configure_network(config).await
+    }
+
+    /// A callback state only signalled by the sidecar of this instance,
+    /// unique to every network change.
+    fn private_callback_state(&self) -> String {
+        let change = self.network_changes.fetch_add(1, Ordering::Relaxed) + 1;
+
+        format!("network-changed-{}-{}", self.global_seq, change)
+    }
+
+    pub fn record_message(&self, message: impl Into<Cow<'static, str>>) {
+        let message = message.into().into_owned();
+
+        let event = EventType::Message { message };
+
+        let json_event =
+            serde_json::to_string(&self.log_line(&event)).expect("Event Serialization");
+
+        println!("{}", json_event);
+
+        self.stats.messages.fetch_add(1, Ordering::Relaxed);
+
+        self.write(&event);
+    }
+
+    /// ```record_message_with``` records a message like
+    /// [`Client::record_message`], along with structured `fields`, e.g. a peer
+    /// ID or an iteration number.
+    ///
+    /// As the message event of the Testground daemon only carries a message,
+    /// the fields are appended to it as a JSON object, e.g.
+    /// `dialed {"iteration":3,"peer":"a"}`, keeping the message readable.
+    pub fn record_message_with(
+        &self,
+        message: impl Into<Cow<'static, str>>,
+        fields: impl IntoIterator<Item = (String, serde_json::Value)>,
+    ) {
+        self.record_message(with_fields(message.into(), fields));
+    }
+
+    /// Installs a [`TestgroundLogger`] as the global logger of the [`log`]
+    /// facade, recording log records of at least [`log::Level::Info`] as
+    /// messages. Use [`log::set_max_level`] afterwards for another level.
+    ///
+    /// Fails if a global logger is already installed.
+    pub fn init_logger(&self) -> Result<(), log::SetLoggerError> {
+        log::set_boxed_logger(Box::new(TestgroundLogger::new(self.clone())))?;
+        log::set_max_level(log::LevelFilter::Info);
+
+        Ok(())
+    }
+
+    /// Sets the user-provided object included in `summary.json`, see
+    /// [`ClientBuilder::write_summary`].
+    pub fn set_summary(&self, summary: serde_json::Value) {
+        *self.stats.summary.lock().unwrap() = Some(summary);
+    }
+
+    /// ```record_success``` records the success of this instance.
+    ///
+    /// Requests are handled in order, thus metrics recorded beforehand by
+    /// this client or its clones are written before the outcome is published,
+    /// and never lost to the process exiting right after. The same holds for
+    /// [`Client::record_failure`] and [`Client::record_crash`].
+    pub async fn record_success(self) -> Result<(), Error> {
+        self.claim_outcome()?;
+
+        let (sender, receiver) = oneshot::channel();
+
+        let cmd = Command::SignalSuccess { sender };
+
+        self.request(cmd, receiver).await?;
+
+        self.write_summary(OutcomeKind::Success, None);
 
         self.write(&EventType::Success {
             group: self.run_parameters.test_group_id.clone(),
@@ -295,6 +1442,8 @@ impl Client {
     }
 
     pub async fn record_failure(self, error: impl Into<Cow<'static, str>>) -> Result<(), Error> {
+        self.claim_outcome()?;
+
         let error = error.into().into_owned();
 
         let (sender, receiver) = oneshot::channel();
@@ -304,9 +1453,9 @@ impl Client {
             sender,
         };
 
-        self.cmd_tx.send(cmd).await.expect(BACKGROUND_RECEIVER);
+        self.request(cmd, receiver).await?;
 
-        receiver.await.expect(BACKGROUND_SENDER)?;
+        self.write_summary(OutcomeKind::Failure, Some(&error));
 
         self.write(&EventType::Failure {
             group: self.run_parameters.test_group_id.clone(),
@@ -316,11 +1465,62 @@ impl Client {
         Ok(())
     }
 
+    /// ```record_failure_and_return``` records a failure like
+    /// [`Client::record_failure`] and then fails with [`Error::Failure`], e.g.
+    /// on a failed assertion, to propagate out of `main`:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let client: testground::client::Client = todo!();
+    /// # let latency = 0;
+    /// if latency > 100 {
+    ///     client.record_failure_and_return("latency above 100 ms").await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// The failure event is acknowledged by the sync service before
+    /// returning, thus it is never lost to the process exiting before the
+    /// event was sent. Fails with the error of [`Client::record_failure`]
+    /// instead, if recording the failure fails.
+    pub async fn record_failure_and_return(
+        self,
+        error: impl Into<Cow<'static, str>>,
+    ) -> Result<Infallible, Error> {
+        let error = error.into();
+
+        self.record_failure(error.clone()).await?;
+
+        Err(Error::Failure(error.into_owned()))
+    }
+
+    /// ```record_failure_and_exit``` records a failure like
+    /// [`Client::record_failure`] and then exits the process with status `1`,
+    /// e.g. on a failed assertion deep within a plan.
+    ///
+    /// Like [`Client::record_failure_and_return`], the failure event is
+    /// acknowledged by the sync service before exiting. Note that destructors
+    /// don't run, see [`std::process::exit`].
+    pub async fn record_failure_and_exit(self, error: impl Into<Cow<'static, str>>) -> ! {
+        let error = error.into();
+
+        if let Err(e) = self.record_failure(error.clone()).await {
+            log::error!("{} (failed to record the failure: {})", error, e);
+        }
+
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+
+        std::process::exit(1)
+    }
+
     pub async fn record_crash(
         self,
         error: impl Into<Cow<'static, str>>,
         stacktrace: impl Into<Cow<'static, str>>,
     ) -> Result<(), Error> {
+        self.claim_outcome()?;
+
         let error = error.into().into_owned();
         let stacktrace = stacktrace.into().into_owned();
 
@@ -332,9 +1532,9 @@ impl Client {
             sender,
         };
 
-        self.cmd_tx.send(cmd).await.expect(BACKGROUND_RECEIVER);
+        self.request(cmd, receiver).await?;
 
-        receiver.await.expect(BACKGROUND_SENDER)?;
+        self.write_summary(OutcomeKind::Crash, Some(&error));
 
         self.write(&EventType::Crash {
             groups: self.run_parameters.test_group_id.clone(),
@@ -345,24 +1545,400 @@ impl Client {
         Ok(())
     }
 
-    pub async fn record_metric(&self, write_query: WriteQuery) -> Result<(), Error> {
-        let (sender, receiver) = oneshot::channel();
+    /// ```failure_guard``` returns a guard recording a crash if dropped while
+    /// panicking, e.g. held for the duration of a test plan's `main`, so
+    /// that a panic doesn't end the instance without an outcome:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = testground::client::Client::new_and_init().await?;
+    /// let _guard = client.failure_guard();
+    ///
+    /// // A panic from here on records a crash with its message and backtrace.
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// As dropping can't await, the crash is sent without blocking the
+    /// background task, and the dropping thread waits up to 5 seconds for the
+    /// sync service to acknowledge it. Within a current-thread runtime, the
+    /// background task runs on the dropping thread, thus the crash is queued
+    /// without waiting, and published once the runtime runs again, e.g. after
+    /// catching the panic, at the latest by [`Client::close`]. The crash is
+    /// written to `run.out` regardless.
+    ///
+    /// As unwinding drops the frames of the panic before the guard, the first
+    /// guard of a client installs a panic hook capturing the message, location
+    /// and backtrace of panics, after running the previously installed hook.
+    pub fn failure_guard(&self) -> FailureGuard {
+        if !self.panics.hooked.swap(true, Ordering::AcqRel) {
+            let panics = self.panics.clone();
+            let previous = std::panic::take_hook();
+
+            std::panic::set_hook(Box::new(move |info| {
+                previous(info);
+
+                let (error, stacktrace) = crash_of_panic(info.payload(), info.location());
+                *panics.last() = Some((std::thread::current().id(), error, stacktrace));
+            }));
+        }
 
-        let cmd = Command::Metric {
-            write_query,
+        FailureGuard {
+            client: self.clone(),
+        }
+    }
+
+    /// ```install_panic_hook``` installs a panic hook recording a crash with
+    /// the panic message, its location and a backtrace, like
+    /// [`Client::failure_guard`], after running the previously installed hook,
+    /// e.g. printing the panic to stderr.
+    ///
+    /// Only the first panic is recorded, as the outcome of an instance is
+    /// recorded at most once. Note that the hook holds a clone of this client,
+    /// keeping the connection to the sync service open.
+    ///
+    /// Like [`Client::failure_guard`], the panicking thread waits up to 5
+    /// seconds for the sync service to acknowledge the crash, unless within a
+    /// current-thread runtime, where the crash is published once the runtime
+    /// runs again, at the latest by [`Client::close`].
+    pub fn install_panic_hook(&self) {
+        let client = self.clone();
+        let previous = std::panic::take_hook();
+
+        std::panic::set_hook(Box::new(move |info| {
+            previous(info);
+
+            let (error, stacktrace) = crash_of_panic(info.payload(), info.location());
+            client.record_crash_blocking(error, stacktrace, BLOCKING_CRASH_TIMEOUT);
+        }));
+    }
+
+    /// Records a crash like [`Client::record_crash`], but without an async
+    /// context, waiting at most `timeout` for the sync service, unless within
+    /// a current-thread runtime.
+    fn record_crash_blocking(&self, error: String, stacktrace: String, timeout: Duration) {
+        if self.claim_outcome().is_err() {
+            return;
+        }
+
+        let (sender, mut receiver) = oneshot::channel();
+
+        let cmd = Command::SignalCrash {
+            error: error.clone(),
+            stacktrace: stacktrace.clone(),
+            sender,
+        };
+
+        // Within a current-thread runtime, the background task runs on this
+        // very thread, thus only publishes the crash once this thread returns
+        // to the runtime.
+        let wait = !matches!(
+            tokio::runtime::Handle::try_current().map(|handle| handle.runtime_flavor()),
+            Ok(tokio::runtime::RuntimeFlavor::CurrentThread)
+        );
+
+        if self.blocking_tx.send(cmd).is_ok() && wait {
+            // Blocking on the receiver would panic within a runtime.
+            let deadline = std::time::Instant::now() + timeout;
+            loop {
+                match receiver.try_recv() {
+                    Err(oneshot::error::TryRecvError::Empty)
+                        if std::time::Instant::now() < deadline =>
+                    {
+                        std::thread::sleep(Duration::from_millis(10));
+                    }
+                    Ok(Ok(_)) => break,
+                    // Not `log::error!`, as the logger of `Client::init_logger`
+                    // routes records back into this client, from within a panic.
+                    Ok(Err(e)) => {
+                        eprintln!("Failed to record the crash: {}", e);
+                        break;
+                    }
+                    Err(_) => {
+                        eprintln!("Failed to record the crash: no acknowledgement");
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.write_summary(OutcomeKind::Crash, Some(&error));
+
+        self.write(&EventType::Crash {
+            groups: self.run_parameters.test_group_id.clone(),
+            error,
+            stacktrace,
+        });
+    }
+
+    /// Claims the outcome of the instance for this clone, failing if another
+    /// clone already recorded one.
+    fn claim_outcome(&self) -> Result<(), Error> {
+        match self.outcome_recorded.swap(true, Ordering::AcqRel) {
+            true => Err(Error::OutcomeAlreadyRecorded),
+            false => Ok(()),
+        }
+    }
+
+    /// Sends `cmd` and awaits its response on `receiver`, failing with
+    /// [`Error::Closed`] rather than panicking if another clone closed the
+    /// connection.
+    async fn request<T>(
+        &self,
+        cmd: Command,
+        receiver: oneshot::Receiver<Result<T, Error>>,
+    ) -> Result<T, Error> {
+        self.cmd_tx.send(cmd).await.map_err(|_| Error::Closed)?;
+
+        receiver.await.map_err(|_| Error::Closed)?
+    }
+
+    /// Sends a subscription `cmd`, yielding [`Error::Closed`] on its stream
+    /// rather than panicking if another clone closed the connection.
+    async fn subscription(&self, cmd: Command) {
+        match self.cmd_tx.send(cmd).await {
+            Err(SendError(Command::Subscribe { stream, .. }))
+            | Err(SendError(Command::GroupSubscribe { stream, .. })) => {
+                let _ = stream.try_send(Err(Error::Closed));
+            }
+            _ => {}
+        }
+    }
+
+    /// ```record_metric``` records a metric, either a [`WriteQuery`] or a
+    /// [`Point`](crate::metrics::Point).
+    #[cfg(feature = "metrics")]
+    pub async fn record_metric(&self, write_query: impl Into<WriteQuery>) -> Result<(), Error> {
+        let (sender, receiver) = oneshot::channel();
+
+        let cmd = Command::Metric {
+            write_query: write_query.into(),
+            sender,
+        };
+
+        self.request(cmd, receiver).await?;
+
+        self.stats.metrics.fetch_add(1, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Metrics are disabled at compile time, thus recording one is a no-op.
+    ///
+    /// Enable the `metrics` feature to write metrics to InfluxDB.
+    #[cfg(not(feature = "metrics"))]
+    pub async fn record_metric(&self, _write_query: impl Into<WriteQuery>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// ```record_metric_with``` records the metric built by `build`, calling
+    /// it only if metrics are enabled, see `test_disable_metrics`, thus
+    /// sparing the cost of building discarded metrics in hot loops.
+    #[cfg(feature = "metrics")]
+    pub async fn record_metric_with<Q: Into<WriteQuery>>(
+        &self,
+        build: impl FnOnce() -> Q,
+    ) -> Result<(), Error> {
+        if self.run_parameters.test_disable_metrics {
+            return Ok(());
+        }
+
+        self.record_metric(build()).await
+    }
+
+    /// Metrics are disabled at compile time, thus `build` is never called.
+    ///
+    /// Enable the `metrics` feature to write metrics to InfluxDB.
+    #[cfg(not(feature = "metrics"))]
+    pub async fn record_metric_with<Q: Into<WriteQuery>>(
+        &self,
+        _build: impl FnOnce() -> Q,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// ```record_metrics``` records a batch of metrics in a single write,
+    /// e.g. when emitting thousands of points.
+    ///
+    /// The batch succeeds or fails as a whole. A point failing to build is
+    /// reported as [`Error::InvalidMetric`], pointing out its index.
+    #[cfg(feature = "metrics")]
+    pub async fn record_metrics(
+        &self,
+        write_queries: impl IntoIterator<Item = impl Into<WriteQuery>>,
+    ) -> Result<(), Error> {
+        let write_queries: Vec<_> = write_queries.into_iter().map(Into::into).collect();
+
+        if write_queries.is_empty() {
+            return Ok(());
+        }
+
+        let (sender, receiver) = oneshot::channel();
+
+        let count = write_queries.len() as u64;
+        let cmd = Command::MetricBatch {
+            write_queries,
             sender,
         };
 
-        self.cmd_tx.send(cmd).await.expect(BACKGROUND_RECEIVER);
+        self.request(cmd, receiver).await?;
+
+        self.stats.metrics.fetch_add(count, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Metrics are disabled at compile time, thus recording them is a no-op.
+    ///
+    /// Enable the `metrics` feature to write metrics to InfluxDB.
+    #[cfg(not(feature = "metrics"))]
+    pub async fn record_metrics(
+        &self,
+        _write_queries: impl IntoIterator<Item = impl Into<WriteQuery>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// ```set_global_tags``` replaces the tags added to every metric recorded
+    /// by this client and its clones from now on.
+    ///
+    /// Defaults to `run_id`, `group_id` and `instance_seq`, i.e. the global
+    /// sequence number of this instance.
+    #[cfg(feature = "metrics")]
+    pub async fn set_global_tags(&self, tags: HashMap<String, String>) {
+        let cmd = Command::SetGlobalTags { tags };
+
+        // There is nothing left to tag once closed.
+        let _ = self.cmd_tx.send(cmd).await;
+    }
+
+    /// Metrics are disabled at compile time, thus there is nothing to tag.
+    #[cfg(not(feature = "metrics"))]
+    pub async fn set_global_tags(&self, _tags: HashMap<String, String>) {}
+
+    /// ```record_result_metric``` records `point` as a results metric, i.e.
+    /// prefixing its measurement with `results.`, see
+    /// [`metrics::RESULTS_PREFIX`].
+    ///
+    /// Results and diagnostics metrics are written to the same `testground`
+    /// database as other metrics, told apart by the prefix of their
+    /// measurement, like the Go SDK does.
+    pub async fn record_result_metric(&self, point: metrics::Point) -> Result<(), Error> {
+        self.record_metric(point.prefixed(metrics::RESULTS_PREFIX))
+            .await
+    }
+
+    /// ```record_diagnostic_metric``` records `point` as a diagnostics
+    /// metric, i.e. prefixing its measurement with `diagnostics.`, see
+    /// [`Client::record_result_metric`].
+    pub async fn record_diagnostic_metric(&self, point: metrics::Point) -> Result<(), Error> {
+        self.record_metric(point.prefixed(metrics::DIAGNOSTICS_PREFIX))
+            .await
+    }
+
+    /// ```record_counter``` records the value of a counter, e.g. the number
+    /// of messages received so far, as the `count` field of the
+    /// `results.<name>` measurement.
+    ///
+    /// Like all metrics, the point is tagged with the global tags, see
+    /// [`Client::set_global_tags`], allowing dashboards to select the points
+    /// of a run or an instance, e.g. `SELECT last("count") FROM
+    /// "results.messages" WHERE "run_id" = '...' GROUP BY "instance_seq"`.
+    pub async fn record_counter(&self, name: &str, value: u64) -> Result<(), Error> {
+        self.record_point(name, [("count".to_owned(), value.into())])
+            .await
+    }
+
+    /// ```record_gauge``` records the current value of a gauge, e.g. the
+    /// number of connected peers, as the `value` field of the
+    /// `results.<name>` measurement, see [`Client::record_counter`].
+    pub async fn record_gauge(&self, name: &str, value: f64) -> Result<(), Error> {
+        self.record_point(name, [("value".to_owned(), value.into())])
+            .await
+    }
+
+    /// ```record_point``` records a point of the `results.<measurement>`
+    /// measurement carrying `fields`, see [`Client::record_counter`].
+    pub async fn record_point(
+        &self,
+        measurement: &str,
+        fields: impl IntoIterator<Item = (String, FieldValue)>,
+    ) -> Result<(), Error> {
+        self.record_metric(metrics::result_point(measurement, fields))
+            .await
+    }
+
+    /// ```now_timestamp``` returns the current time in nanoseconds, the
+    /// precision of the timestamps expected by the Testground dashboards,
+    /// e.g. for a [`WriteQuery`] recorded with [`Client::record_metric`].
+    pub fn now_timestamp() -> Timestamp {
+        metrics::now()
+    }
+
+    /// ```record_histogram``` flushes the samples accumulated by the
+    /// [`Histogram`] and records the resulting summary point.
+    ///
+    /// Nothing is recorded if the histogram holds no sample.
+    pub async fn record_histogram(&self, histogram: &mut Histogram) -> Result<(), Error> {
+        match histogram.flush(Self::now_timestamp()) {
+            Some(write_query) => self.record_metric(write_query).await,
+            None => Ok(()),
+        }
+    }
+
+    /// ```write_results_csv``` writes `rows` as CSV to the file `name` within
+    /// the outputs directory, see [`RunParameters::output_file`], e.g. latency
+    /// samples for offline analysis.
+    ///
+    /// Rows must be structs of scalar fields, named by the header: fields of
+    /// other types are written as JSON and `None` as an empty cell. The file
+    /// is synced to disk before returning, thus never collected partially
+    /// written once the instance exits.
+    pub fn write_results_csv<T: Serialize>(
+        &self,
+        name: &str,
+        rows: impl IntoIterator<Item = T>,
+    ) -> Result<(), Error> {
+        let file = self.run_parameters.output_file(name)?;
+
+        let mut writer = std::io::BufWriter::new(file);
+        csv::write(&mut writer, rows)?;
 
-        receiver.await.expect(BACKGROUND_SENDER)?;
+        let file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
 
         Ok(())
     }
 
+    /// ```close``` shuts down the background task, closing the connection to
+    /// the sync service.
+    ///
+    /// Note that requests still pending, including those of other clones of
+    /// this client, are dropped. Requests of other clones from then on fail
+    /// with [`Error::Closed`], and their subscriptions yield it before
+    /// ending.
+    pub async fn close(self) -> Result<(), Error> {
+        let (sender, receiver) = oneshot::channel();
+
+        let cmd = Command::Shutdown { sender };
+
+        self.cmd_tx.send(cmd).await.map_err(|_| Error::Closed)?;
+
+        receiver.await.map_err(|_| Error::Closed)?
+    }
+
+    /// Returns the number of live clones of this client, including this one
+    /// and the one held by the logger installed with [`Client::init_logger`].
+    ///
+    /// Once it drops to one, this is the last clone, and dropping it closes
+    /// the connection to the sync service.
+    pub fn clone_count(&self) -> usize {
+        Arc::strong_count(&self.outcome_recorded)
+    }
+
     /// Returns runtime parameters for this test.
-    pub fn run_parameters(&self) -> RunParameters {
-        self.run_parameters.clone()
+    pub fn run_parameters(&self) -> &RunParameters {
+        &self.run_parameters
     }
 
     /// Returns a global sequence number assigned to this test instance.
@@ -375,24 +1951,1769 @@ impl Client {
         self.group_seq
     }
 
+    /// Returns whether this instance is the leader of the run, by the
+    /// convention of the instance with global sequence number 1 leading.
+    pub fn is_global_leader(&self) -> bool {
+        self.global_seq == 1
+    }
+
+    /// Returns whether this instance is the leader of its group, by the
+    /// convention of the instance with group sequence number 1 leading.
+    pub fn is_group_leader(&self) -> bool {
+        self.group_seq == 1
+    }
+
+    /// Returns the time left until the run deadline, see
+    /// [`RunParameters::run_deadline`], or `None` if the run has no deadline.
+    ///
+    /// Once the deadline is reached, [`Duration::ZERO`] is returned.
+    pub fn time_until_deadline(&self) -> Option<Duration> {
+        let deadline = self.run_parameters.run_deadline()?;
+        let now = chrono::DateTime::<chrono::Utc>::from(SystemTime::now());
+
+        Some(
+            (deadline.with_timezone(&chrono::Utc) - now)
+                .to_std()
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Returns whether the run deadline has been reached, allowing plans to
+    /// wind down before being killed by the daemon.
+    ///
+    /// Always `false` if the run has no deadline.
+    pub fn deadline_reached(&self) -> bool {
+        self.time_until_deadline() == Some(Duration::ZERO)
+    }
+
+    /// Returns a seed derived from the run, the group, the given `salt` and
+    /// the global sequence number of this instance.
+    ///
+    /// Unlike [`RunParameters::deterministic_seed`], the seed is distinct per
+    /// instance while remaining stable across re-runs of the same run.
+    pub fn deterministic_seed(&self, salt: &str) -> u64 {
+        self.run_parameters.seed(salt, Some(self.global_seq))
+    }
+
+    /// Scopes `state` to the group of this instance.
+    ///
+    /// States are only contextualized with the run, plan and case, thus the
+    /// same state signalled by different groups would share a counter.
+    fn group_state(&self, state: impl Into<Cow<'static, str>>) -> String {
+        format!("{}_{}", state.into(), self.run_parameters.test_group_id)
+    }
+
+    /// Returns the items to publish for `message`, see [`Chunker::split`].
+    fn fragments(&self, message: serde_json::Value) -> Vec<serde_json::Value> {
+        match &self.chunker {
+            Some(chunker) => chunker.split(self.global_seq, message),
+            None => vec![message],
+        }
+    }
+
+    /// Writes `summary.json` to the outputs directory, if enabled.
+    fn write_summary(&self, outcome: OutcomeKind, error: Option<&str>) {
+        if !self.write_summary {
+            return;
+        }
+
+        let path = match self.run_parameters.output_path_if_set("summary.json") {
+            Some(path) => path,
+            None => return,
+        };
+
+        let summary = self.stats.summary.lock().unwrap().clone();
+
+        let summary = RunSummary {
+            outcome,
+            group: &self.run_parameters.test_group_id,
+            global_seq: self.global_seq,
+            group_seq: self.group_seq,
+            duration_ms: self.stats.start.elapsed().as_millis(),
+            messages: self.stats.messages.load(Ordering::Relaxed),
+            metrics: self.stats.metrics.load(Ordering::Relaxed),
+            error,
+            summary,
+        };
+
+        if let Err(e) = std::fs::write(
+            path,
+            serde_json::to_vec(&summary).expect("Summary Serialization"),
+        ) {
+            // Not `log::error!`, as the logger of `Client::init_logger` routes
+            // records back into this client.
+            eprintln!("Failed to write `summary.json`: {}", e);
+        }
+    }
+
+    fn log_line<'a>(&'a self, event_type: &'a EventType) -> LogLine<'a> {
+        // Sequence numbers start with 1, once assigned.
+        let instance_seq = (self.global_seq != 0).then_some(self.global_seq);
+
+        LogLine::new(event_type, &self.run_parameters, instance_seq)
+    }
+
     /// Writes an event to `run.out`.
     fn write(&self, event_type: &EventType) {
-        if let Some(path) = self.run_out.as_ref() {
-            let mut file = match File::options().create(true).append(true).open(path) {
-                Ok(file) => file,
-                Err(e) => {
-                    eprintln!("Failed to open `run.out`: {}", e);
-                    return;
-                }
-            };
-
-            if let Err(e) = writeln!(
-                file,
-                "{}",
-                serde_json::to_string(&LogLine::new(event_type)).expect("Event Serialization")
+        if let Some(run_out) = self.run_out.as_ref() {
+            if let Err(e) = run_out.write_line(
+                &serde_json::to_string(&self.log_line(event_type)).expect("Event Serialization"),
             ) {
+                // Not `log::error!`, as the logger of `Client::init_logger`
+                // would write the record to `run.out` again.
                 eprintln!("Failed to write a log to `run.out`: {}", e);
             }
         }
     }
 }
+
+/// A runtime owned by a [`Client`], see [`Client::new_and_init_blocking`].
+struct OwnedRuntime(Option<tokio::runtime::Runtime>);
+
+impl Drop for OwnedRuntime {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks on its tasks, which panics within the
+        // runtime, e.g. when the last clone is dropped by one of its tasks.
+        if let Some(runtime) = self.0.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// Records a crash if dropped while panicking, see [`Client::failure_guard`].
+#[must_use = "the crash is recorded when the guard is dropped"]
+pub struct FailureGuard {
+    client: Client,
+}
+
+impl Drop for FailureGuard {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            return;
+        }
+
+        let (error, stacktrace) = self.client.panics.take_current().unwrap_or_else(|| {
+            (
+                "panicked".to_owned(),
+                std::backtrace::Backtrace::force_capture().to_string(),
+            )
+        });
+
+        self.client
+            .record_crash_blocking(error, stacktrace, BLOCKING_CRASH_TIMEOUT);
+    }
+}
+
+/// The last panic, captured by the panic hook of [`Client::failure_guard`].
+#[derive(Default)]
+struct PanicCapture {
+    hooked: AtomicBool,
+    last: Mutex<Option<(ThreadId, String, String)>>,
+}
+
+impl PanicCapture {
+    /// Locks the last panic, even if poisoned, as panicking again while
+    /// panicking aborts.
+    fn last(&self) -> MutexGuard<'_, Option<(ThreadId, String, String)>> {
+        self.last.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Takes the error and stacktrace of the last panic, if of this thread.
+    fn take_current(&self) -> Option<(String, String)> {
+        let mut last = self.last();
+
+        match last.take() {
+            Some((thread, error, stacktrace)) if thread == std::thread::current().id() => {
+                Some((error, stacktrace))
+            }
+            other => {
+                *last = other;
+                None
+            }
+        }
+    }
+}
+
+/// The error and stacktrace of a crash recorded for a panic, i.e. its message,
+/// and its location followed by a backtrace.
+fn crash_of_panic(
+    payload: &(dyn std::any::Any + Send),
+    location: Option<&std::panic::Location>,
+) -> (String, String) {
+    let stacktrace = std::backtrace::Backtrace::force_capture();
+    let stacktrace = match location {
+        Some(location) => format!("{}\n{}", location, stacktrace),
+        None => stacktrace.to_string(),
+    };
+
+    (panic_message(payload), stacktrace)
+}
+
+/// The message of a panic, i.e. its payload if a string.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => (*message).to_owned(),
+        None => match payload.downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => "Box<dyn Any>".to_owned(),
+        },
+    }
+}
+
+/// Appends `fields` to `message` as a JSON object, if any.
+fn with_fields(
+    message: Cow<'static, str>,
+    fields: impl IntoIterator<Item = (String, serde_json::Value)>,
+) -> Cow<'static, str> {
+    let fields: serde_json::Map<_, _> = fields.into_iter().collect();
+
+    if fields.is_empty() {
+        return message;
+    }
+
+    format!("{} {}", message, serde_json::Value::Object(fields)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    use crate::{
+        errors::InitError, params::test_run_parameters, testing::MockSyncService, RunnerKind,
+    };
+
+    fn builder(sync_service: &MockSyncService, params: RunParameters) -> ClientBuilder {
+        let addr = sync_service.addr();
+
+        ClientBuilder::new()
+            .run_parameters(params)
+            .sync_service_addr(addr.ip().to_string(), addr.port())
+    }
+
+    fn outputs_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("testground-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn summary_written_on_success() {
+        let sync_service = MockSyncService::start().await;
+
+        let mut params = test_run_parameters();
+        params.test_outputs_path = outputs_path("summary");
+
+        let client = builder(&sync_service, params.clone())
+            .write_summary(true)
+            .build()
+            .await
+            .unwrap();
+
+        client.record_message("hello");
+        client.set_summary(serde_json::json!({ "peers": 3 }));
+        client.record_success().await.unwrap();
+
+        let summary: serde_json::Value = serde_json::from_slice(
+            &std::fs::read(params.test_outputs_path.join("summary.json")).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(summary["outcome"], "success");
+        assert_eq!(summary["group"], "single");
+        assert_eq!(summary["global_seq"], 1);
+        assert_eq!(summary["group_seq"], 1);
+        // Including the message recording the claimed sequence numbers.
+        assert_eq!(summary["messages"], 2);
+        assert_eq!(summary["metrics"], 0);
+        assert_eq!(summary["summary"], serde_json::json!({ "peers": 3 }));
+        assert!(summary["duration_ms"].is_u64());
+        assert!(summary.get("error").is_none());
+
+        std::fs::remove_dir_all(params.test_outputs_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn signal_and_wait_group_targets_group() {
+        let sync_service = MockSyncService::start().await;
+
+        let mut group_a = test_run_parameters();
+        group_a.test_instance_count = 3;
+        group_a.test_group_id = "a".to_owned();
+        group_a.test_group_instance_count = 2;
+
+        let mut group_b = group_a.clone();
+        group_b.test_group_id = "b".to_owned();
+        group_b.test_group_instance_count = 1;
+
+        let (a1, a2, b) = tokio::join!(
+            builder(&sync_service, group_a.clone()).build(),
+            builder(&sync_service, group_a).build(),
+            builder(&sync_service, group_b).build(),
+        );
+        let (a1, a2, b) = (a1.unwrap(), a2.unwrap(), b.unwrap());
+
+        // The lone instance of group `b` neither waits for, nor is counted by,
+        // the instances of group `a`.
+        assert_eq!(b.signal_and_wait_group("ready").await.unwrap(), 1);
+
+        let (seq1, seq2) = tokio::join!(
+            a1.signal_and_wait_group("ready"),
+            a2.signal_and_wait_group("ready"),
+        );
+        let mut seqs = [seq1.unwrap(), seq2.unwrap()];
+        seqs.sort();
+        assert_eq!(seqs, [1, 2]);
+
+        b.barrier_group("ready").await.unwrap();
+        a1.barrier_group("ready").await.unwrap();
+
+        #[allow(deprecated)]
+        let seq = b.group_signal_and_wait("done").await.unwrap();
+        assert_eq!(seq, 1);
+    }
+
+    #[tokio::test]
+    async fn leaders() {
+        let sync_service = MockSyncService::start().await;
+
+        let mut params = test_run_parameters();
+        params.test_instance_count = 2;
+        params.test_group_instance_count = 1;
+
+        let mut other_group = params.clone();
+        other_group.test_group_id = "other".to_owned();
+
+        let (a, b) = tokio::join!(
+            builder(&sync_service, params).build(),
+            builder(&sync_service, other_group).build(),
+        );
+        let (a, b) = (a.unwrap(), b.unwrap());
+
+        // Each is the leader of its group, but only one leads the run.
+        assert!(a.is_group_leader() && b.is_group_leader());
+        assert!(a.is_global_leader() != b.is_global_leader());
+    }
+
+    #[tokio::test]
+    async fn signal_and_wait_all_targets_run() {
+        let sync_service = MockSyncService::start().await;
+
+        let mut params = test_run_parameters();
+        params.test_instance_count = 2;
+
+        let (a, b) = tokio::join!(
+            builder(&sync_service, params.clone()).build(),
+            builder(&sync_service, params).build(),
+        );
+        let (a, b) = (a.unwrap(), b.unwrap());
+
+        let (a, b, barrier) = tokio::join!(
+            a.signal_and_wait_all("ready"),
+            b.signal_and_wait_all("ready"),
+            b.barrier_all("ready"),
+        );
+        let mut seqs = [a.unwrap(), b.unwrap()];
+        seqs.sort();
+        assert_eq!(seqs, [1, 2]);
+        barrier.unwrap();
+    }
+
+    #[tokio::test]
+    async fn signal_and_snapshot() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        client.signal_entry("a").await.unwrap();
+        client.signal_entry("a").await.unwrap();
+        client.signal_entry("b").await.unwrap();
+
+        let (seq, snapshot) = client
+            .signal_and_snapshot("c", &["a".to_owned(), "b".to_owned(), "d".to_owned()])
+            .await
+            .unwrap();
+
+        assert_eq!(seq, 1);
+        assert_eq!(
+            snapshot,
+            HashMap::from([
+                ("a".to_owned(), 2),
+                ("b".to_owned(), 1),
+                ("d".to_owned(), 0)
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn barrier_any_cancels_others() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let any = {
+            let client = client.clone();
+            tokio::spawn(async move {
+                client
+                    .barrier_any(vec![
+                        ("leader-elected".to_owned(), 1),
+                        ("election-timed-out".to_owned(), 1),
+                    ])
+                    .await
+            })
+        };
+
+        while sync_service.pending_barriers() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        client.signal_entry("election-timed-out").await.unwrap();
+
+        assert_eq!(any.await.unwrap().unwrap(), "election-timed-out");
+
+        while sync_service.pending_barriers() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn barrier_with_timeout() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let res = client
+            .barrier_with_timeout("never-reached", 1, Duration::from_millis(100))
+            .await;
+        assert!(matches!(
+            res,
+            Err(Error::Timeout { ref state, target: 1, .. }) if state == "never-reached"
+        ));
+
+        // The abandoned barrier is cancelled, thus a late signal finds no
+        // pending request to respond to.
+        while sync_service.pending_barriers() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        client.signal_entry("never-reached").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn signal_and_wait_timeout() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let res = client
+            .signal_and_wait_timeout("staged", 2, Duration::from_millis(100))
+            .await;
+        assert!(matches!(
+            res,
+            Err(Error::Timeout { ref state, target: 2, .. }) if state == "staged"
+        ));
+
+        // The entry registered before timing out.
+        assert_eq!(client.state_count("staged").await.unwrap(), 1);
+        while sync_service.pending_barriers() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribe_idle_timeout() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let mut stream = client
+            .subscribe_idle_timeout("stalling", 16, Duration::from_millis(100))
+            .await;
+
+        let publisher = client.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            publisher
+                .publish("stalling", Cow::Owned(serde_json::json!("late")))
+                .await
+                .unwrap();
+        });
+
+        assert!(matches!(
+            stream.next().await.unwrap(),
+            Err(Error::IdleTimeout(_))
+        ));
+
+        // The stream remains open, eventually yielding the delayed message.
+        let item = loop {
+            match stream.next().await.unwrap() {
+                Err(Error::IdleTimeout(_)) => continue,
+                item => break item.unwrap(),
+            }
+        };
+        assert_eq!(item, serde_json::json!("late"));
+    }
+
+    #[tokio::test]
+    async fn group_topics_are_private() {
+        let sync_service = MockSyncService::start().await;
+
+        let mut params = test_run_parameters();
+        params.test_instance_count = 2;
+
+        let mut group_a = params.clone();
+        group_a.test_group_id = "a".to_owned();
+        let mut group_b = params;
+        group_b.test_group_id = "b".to_owned();
+
+        let (a, b) = tokio::join!(
+            builder(&sync_service, group_a).build(),
+            builder(&sync_service, group_b).build(),
+        );
+        let (a, b) = (a.unwrap(), b.unwrap());
+
+        a.group_publish("peers", Cow::Owned(serde_json::json!("from a")))
+            .await
+            .unwrap();
+        b.group_publish("peers", Cow::Owned(serde_json::json!("from b")))
+            .await
+            .unwrap();
+
+        let topics = sync_service.topics();
+        assert!(topics.contains(
+            &"run:c7fjstge5te621cen4i0:plan:sdk-rust:case:example:groups:a:topics:peers".to_owned()
+        ));
+        assert!(topics.contains(
+            &"run:c7fjstge5te621cen4i0:plan:sdk-rust:case:example:groups:b:topics:peers".to_owned()
+        ));
+
+        // Subscriptions replay the topic, thus an item of the other group would
+        // come first.
+        let mut stream = a.group_subscribe("peers", 16).await;
+        assert_eq!(stream.next().await.unwrap().unwrap(), "from a");
+        let mut stream = b.group_subscribe("peers", 16).await;
+        assert_eq!(stream.next().await.unwrap().unwrap(), "from b");
+    }
+
+    fn network_config() -> NetworkConfiguration {
+        use crate::network_conf::*;
+
+        NetworkConfiguration {
+            network: DEFAULT_DATA_NETWORK.to_owned(),
+            ipv4: None,
+            ipv6: None,
+            enable: true,
+            default: LinkShape {
+                latency: Duration::from_millis(10),
+                jitter: Duration::ZERO,
+                bandwidth: 1048576,
+                filter: FilterAction::Accept,
+                loss: 0.0,
+                corrupt: 0.0,
+                corrupt_corr: 0.0,
+                reorder: 0.0,
+                reorder_corr: 0.0,
+                duplicate: 0.0,
+                duplicate_corr: 0.0,
+            },
+            rules: None,
+            callback_state: "latency-reduced".to_owned(),
+            callback_target: Some(1),
+            routing_policy: RoutingPolicyType::DenyAll,
+        }
+    }
+
+    #[tokio::test]
+    async fn configure_network_unsupported_on_local_exec() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            client.configure_network(network_config()).await,
+            Err(Error::UnsupportedOnRunner(RunnerKind::LocalExec))
+        ));
+    }
+
+    #[tokio::test]
+    async fn configure_network_returns_seq() {
+        let sync_service = MockSyncService::start().await;
+
+        // Plays the part of the sidecar, signalling the network states.
+        let sidecar = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+        sidecar.signal_entry("network-initialized").await.unwrap();
+
+        let mut params = test_run_parameters();
+        params.test_sidecar = true;
+        let client = builder(&sync_service, params).build().await.unwrap();
+
+        let configure = {
+            let client = client.clone();
+            tokio::spawn(async move { client.configure_network(network_config()).await })
+        };
+
+        while sync_service.pending_barriers() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        sidecar.signal_entry("latency-reduced").await.unwrap();
+
+        assert_eq!(configure.await.unwrap().unwrap(), 1);
+
+        // The callback state already reached its target.
+        assert_eq!(client.configure_network(network_config()).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn raw_request() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let res = client
+            .raw_request(serde_json::json!({"signal_entry": {"state": "raw"}}))
+            .await
+            .unwrap();
+        assert_eq!(res["signal_entry"]["seq"], 1);
+
+        // Not a JSON object, thus failing to be sent.
+        assert!(matches!(
+            client.raw_request(serde_json::json!("raw")).await,
+            Err(Error::Serde(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn publish_and_subscribe_bytes() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let bytes = vec![0x08, 0x96, 0x01, 0xff];
+        client.publish_bytes("blobs", &bytes).await.unwrap();
+        // Published by a Go instance as `[]byte{0x08, 0x96}`.
+        client
+            .publish("blobs", Cow::Owned(serde_json::json!("CJY=")))
+            .await
+            .unwrap();
+        client
+            .publish("blobs", Cow::Owned(serde_json::json!("not base64!")))
+            .await
+            .unwrap();
+
+        let mut stream = client.subscribe_bytes("blobs", 16).await;
+        assert_eq!(stream.next().await.unwrap().unwrap(), bytes);
+        assert_eq!(stream.next().await.unwrap().unwrap(), [0x08, 0x96]);
+        assert!(matches!(
+            stream.next().await.unwrap(),
+            Err(Error::Base64(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn stages() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        // Follows the events of the `network-initialized` stage.
+        let start = client.stage_start("warmup").await.unwrap();
+        let end = client.stage_end("warmup").await.unwrap();
+        assert_eq!((start, end), (3, 4));
+    }
+
+    #[tokio::test]
+    async fn configure_multiple_networks() {
+        let sync_service = MockSyncService::start().await;
+
+        let sidecar = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+        sidecar.signal_entry("network-initialized").await.unwrap();
+
+        let mut params = test_run_parameters();
+        params.test_sidecar = true;
+        let client = builder(&sync_service, params.clone())
+            .build()
+            .await
+            .unwrap();
+
+        let configure = |network: &'static str, callback_state: &'static str| {
+            let client = client.clone();
+            let config = NetworkConfigurationBuilder::new(callback_state)
+                .network(network)
+                .callback_target(1)
+                .build();
+            tokio::spawn(async move { client.configure_network(config).await })
+        };
+
+        let default = configure("default", "default-configured");
+        while sync_service.pending_barriers() < 1 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let secondary = configure("secondary", "secondary-configured");
+        while sync_service.pending_barriers() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Applying the secondary network leaves the default one pending.
+        sidecar.signal_entry("secondary-configured").await.unwrap();
+        assert_eq!(secondary.await.unwrap().unwrap(), 2);
+        assert_eq!(sync_service.pending_barriers(), 1);
+
+        sidecar.signal_entry("default-configured").await.unwrap();
+        assert_eq!(default.await.unwrap().unwrap(), 1);
+
+        // Both configurations are published to the topic of the host.
+        let topic = format!("network:{}", params.hostname);
+        let configs = client.subscribe_collect(topic, 2).await.unwrap();
+        assert_eq!(configs[0]["network"], "default");
+        assert_eq!(configs[1]["network"], "secondary");
+    }
+
+    #[tokio::test]
+    async fn disconnect_and_reconnect_network() {
+        let sync_service = MockSyncService::start().await;
+
+        let sidecar = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+        sidecar.signal_entry("network-initialized").await.unwrap();
+
+        let mut params = test_run_parameters();
+        params.test_sidecar = true;
+        let client = builder(&sync_service, params).build().await.unwrap();
+
+        let disconnect = {
+            let client = client.clone();
+            tokio::spawn(async move { client.disconnect_network().await })
+        };
+
+        while sync_service.pending_barriers() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let state = format!("network-changed-{}-1", client.global_seq());
+        sidecar.signal_entry(state).await.unwrap();
+
+        assert_eq!(disconnect.await.unwrap().unwrap(), 1);
+
+        let reconnect = {
+            let client = client.clone();
+            tokio::spawn(async move {
+                client
+                    .reconnect_network(LinkShapeBuilder::new().build())
+                    .await
+            })
+        };
+
+        // The previous callback state already reached its target, thus the
+        // reconnection has to wait for a state of its own.
+        while sync_service.pending_barriers() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let state = format!("network-changed-{}-2", client.global_seq());
+        sidecar.signal_entry(state).await.unwrap();
+
+        assert_eq!(reconnect.await.unwrap().unwrap(), 2);
+    }
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Peer {
+        id: String,
+        addrs: Vec<String>,
+    }
+
+    #[tokio::test]
+    async fn publish_typed() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let peer = Peer {
+            id: "QmbSLMEMackm7vHiUGMB2EFAPbzeJNpeB9yTpzYKoojDWc".to_owned(),
+            addrs: vec!["/ip4/16.3.0.3/tcp/45369".to_owned()],
+        };
+        assert_eq!(client.publish_typed("peers", &peer).await.unwrap(), 1);
+
+        let mut stream = client.subscribe("peers", 16).await;
+        assert_eq!(
+            stream.next().await.unwrap().unwrap(),
+            serde_json::json!({
+                "id": "QmbSLMEMackm7vHiUGMB2EFAPbzeJNpeB9yTpzYKoojDWc",
+                "addrs": ["/ip4/16.3.0.3/tcp/45369"],
+            })
+        );
+
+        // Maps with non-string keys can't be serialized to JSON.
+        let invalid = HashMap::from([((1, 2), "a")]);
+        assert!(matches!(
+            client.publish_typed("peers", &invalid).await,
+            Err(Error::Serialize(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn subscribe_typed() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let peer = Peer {
+            id: "QmbSLMEMackm7vHiUGMB2EFAPbzeJNpeB9yTpzYKoojDWc".to_owned(),
+            addrs: vec!["/ip4/16.3.0.3/tcp/45369".to_owned()],
+        };
+        client.publish_typed("peers", &peer).await.unwrap();
+        client
+            .publish("peers", Cow::Owned(serde_json::json!("")))
+            .await
+            .unwrap();
+        client.publish_typed("peers", &peer).await.unwrap();
+
+        let mut stream = client.subscribe_typed::<Peer>("peers", 16).await;
+        assert_eq!(stream.next().await.unwrap().unwrap(), peer);
+        assert!(matches!(stream.next().await.unwrap(), Err(Error::Serde(_))));
+        assert_eq!(stream.next().await.unwrap().unwrap(), peer);
+    }
+
+    #[tokio::test]
+    async fn close() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(sync_service.connections(), 1);
+
+        client.close().await.unwrap();
+
+        while sync_service.connections() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn record_metrics_reports_invalid_point() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let valid = WriteQuery::new(Timestamp::Nanoseconds(0), "latency").add_field("ms", 1);
+        // A point requires at least one field.
+        let invalid = WriteQuery::new(Timestamp::Nanoseconds(0), "latency");
+
+        assert!(matches!(
+            client.record_metrics(vec![valid, invalid]).await,
+            Err(Error::InvalidMetric { index: 1, .. })
+        ));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn record_metric_disabled_by_run() {
+        let sync_service = MockSyncService::start().await;
+
+        let mut params = test_run_parameters();
+        params.test_disable_metrics = true;
+        let client = builder(&sync_service, params).build().await.unwrap();
+
+        // Neither built nor written, thus the invalid point goes unnoticed.
+        let invalid = WriteQuery::new(Timestamp::Nanoseconds(0), "latency");
+        client.record_metric(invalid.clone()).await.unwrap();
+        client.record_metrics(vec![invalid]).await.unwrap();
+
+        client
+            .record_metric_with(|| -> WriteQuery { panic!("Metric built") })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn subscribe_cancellable() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let (mut stream, subscription) = client.subscribe_cancellable("updates", 16).await;
+
+        client
+            .publish("updates", Cow::Owned(serde_json::json!("first")))
+            .await
+            .unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap(), "first");
+        assert_eq!(sync_service.subscriptions(), 1);
+
+        subscription.cancel();
+
+        assert!(stream.next().await.is_none());
+        while sync_service.subscriptions() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribe_cancelled_on_drop() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let stream = client.subscribe("updates", 16).await;
+        while sync_service.subscriptions() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        drop(stream);
+
+        // Cancelled without another element arriving.
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while sync_service.subscriptions() > 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("Subscription cancelled on drop");
+    }
+
+    #[tokio::test]
+    async fn full_subscription_does_not_stall() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let mut stream = client.subscribe("burst", 1).await;
+
+        let burst = async {
+            for i in 0..8 {
+                client
+                    .publish("burst", Cow::Owned(serde_json::json!(i)))
+                    .await
+                    .unwrap();
+            }
+
+            client.signal_and_wait("burst-sent", 1).await.unwrap();
+        };
+        tokio::time::timeout(Duration::from_secs(5), burst)
+            .await
+            .expect("Publish and signal while the subscription is full");
+
+        for i in 0..8 {
+            assert_eq!(stream.next().await.unwrap().unwrap(), i);
+        }
+    }
+
+    #[tokio::test]
+    async fn disconnect_fails_pending_requests() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let mut stream = client.subscribe("peers", 16).await;
+
+        let (res, ()) = tokio::join!(client.barrier("never-reached", 1), async {
+            while sync_service.pending_barriers() == 0 || sync_service.subscriptions() == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            sync_service.disconnect();
+        });
+
+        assert!(matches!(res, Err(Error::Disconnected)));
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(Error::Disconnected))
+        ));
+    }
+
+    #[tokio::test]
+    async fn reconnect() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .reconnect(ReconnectPolicy {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(10),
+            })
+            .build()
+            .await
+            .unwrap();
+
+        let (res, ()) = tokio::join!(client.barrier("never-reached", 1), async {
+            while sync_service.pending_barriers() == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            sync_service.disconnect();
+        });
+        assert!(matches!(res, Err(Error::Disconnected)));
+
+        // The states of the sync service survive the lost connection.
+        assert_eq!(client.signal_entry("never-reached").await.unwrap(), 1);
+        assert_eq!(sync_service.connections(), 1);
+
+        // The sequence numbers aren't claimed again.
+        assert_eq!((client.global_seq(), client.group_seq()), (1, 1));
+        assert_eq!(client.state_count("initialized_global").await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn keepalive() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .keepalive(Some(Duration::from_millis(10)))
+            .build()
+            .await
+            .unwrap();
+
+        // Pongs don't interfere with responses.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(client.signal_entry("after-pings").await.unwrap(), 1);
+        assert_eq!(sync_service.connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn init_logger() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        client.init_logger().unwrap();
+        assert!(client.init_logger().is_err());
+
+        // Other tests may log concurrently.
+        let messages = client.stats.messages.load(Ordering::Relaxed);
+        log::info!("logged through the facade");
+        log::trace!("filtered by level");
+        assert!(client.stats.messages.load(Ordering::Relaxed) > messages);
+    }
+
+    #[test]
+    fn message_with_fields() {
+        assert_eq!(
+            with_fields(
+                "dialed".into(),
+                [
+                    ("peer".to_owned(), serde_json::json!("a")),
+                    ("iteration".to_owned(), serde_json::json!(3)),
+                ]
+            ),
+            r#"dialed {"iteration":3,"peer":"a"}"#
+        );
+        assert_eq!(with_fields("dialed".into(), []), "dialed");
+    }
+
+    #[tokio::test]
+    async fn subscribe_after() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        for i in 1..=3 {
+            client.publish("items", Cow::Owned(i.into())).await.unwrap();
+        }
+
+        let mut stream = client.subscribe_after("items", 4, 2).await;
+        assert_eq!(stream.next().await.unwrap().unwrap(), 3);
+
+        client.publish("items", Cow::Owned(4.into())).await.unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn subscribe_collect() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        for i in 1..=3 {
+            client.publish("items", Cow::Owned(i.into())).await.unwrap();
+        }
+
+        assert_eq!(
+            client.subscribe_collect("items", 2).await.unwrap(),
+            vec![serde_json::json!(1), serde_json::json!(2)]
+        );
+        while sync_service.subscriptions() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let (res, ()) = tokio::join!(client.subscribe_collect("items", 4), async {
+            while sync_service.subscriptions() == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            sync_service.disconnect();
+        });
+        assert!(matches!(res, Err(Error::Disconnected)));
+    }
+
+    #[tokio::test]
+    async fn publish_and_wait_for() {
+        let sync_service = MockSyncService::start().await;
+
+        let mut params = test_run_parameters();
+        params.test_instance_count = 2;
+
+        let (a, b) = tokio::join!(
+            builder(&sync_service, params.clone()).build(),
+            builder(&sync_service, params).build(),
+        );
+        let (a, b) = (a.unwrap(), b.unwrap());
+
+        let (a, b) = tokio::join!(
+            a.publish_and_wait_for("addrs", Cow::Owned("a".into()), 2),
+            b.publish_and_wait_for("addrs", Cow::Owned("b".into()), 2),
+        );
+        let (a, b) = (a.unwrap(), b.unwrap());
+
+        assert_eq!(a, b);
+        assert!(a.contains(&"a".into()) && a.contains(&"b".into()));
+
+        while sync_service.subscriptions() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn chunked_payload_round_trip() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .chunk_payloads(64)
+            .build()
+            .await
+            .unwrap();
+
+        let large = serde_json::json!({ "addrs": vec!["/ip4/16.3.0.3/tcp/45369"; 16] });
+
+        let seq = client
+            .publish("large", Cow::Owned(large.clone()))
+            .await
+            .unwrap();
+        assert!(seq > 1, "Payload should be split into multiple items.");
+        client
+            .publish("large", Cow::Owned(serde_json::json!("small")))
+            .await
+            .unwrap();
+
+        let mut stream = client.subscribe("large", 16).await;
+        assert_eq!(stream.next().await.unwrap().unwrap(), large);
+        assert_eq!(stream.next().await.unwrap().unwrap(), "small");
+    }
+
+    /// Plan code recording metrics, which has to compile with and without the
+    /// `metrics` feature. Only run without, as there is no InfluxDB to write to.
+    #[cfg_attr(feature = "metrics", allow(dead_code))]
+    async fn record_metrics(client: &Client) -> Result<(), Error> {
+        let mut histogram = Histogram::new("latency").add_tag("peer", "a");
+        histogram.record(1.0);
+        client.record_histogram(&mut histogram).await?;
+
+        let query = WriteQuery::new(Timestamp::Nanoseconds(0), "peers")
+            .add_tag("group", "single")
+            .add_field("connected", 3)
+            .add_field("ratio", 0.5)
+            .add_field("state", "running")
+            .add_field("stable", true)
+            .add_field("limit", None::<u64>);
+        client.record_metric(query).await
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    #[tokio::test]
+    async fn record_metric_noop_without_metrics_feature() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        record_metrics(&client).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn handshake_timeout() {
+        // Accepts connections, but never completes the websocket handshake.
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        let err = ClientBuilder::new()
+            .run_parameters(test_run_parameters())
+            .sync_service_addr(addr.ip().to_string(), addr.port())
+            .handshake_timeout(Duration::from_millis(100))
+            .build()
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            err.downcast_ref::<InitError>(),
+            Some(InitError::HandshakeTimeout(timeout)) if *timeout == Duration::from_millis(100)
+        ));
+    }
+
+    #[tokio::test]
+    async fn record_failure_and_return() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let supervisor = client.clone();
+        let outcomes = tokio::spawn(async move { supervisor.await_all_outcomes(1).await });
+
+        assert!(matches!(
+            client.record_failure_and_return("assertion failed").await,
+            Err(Error::Failure(error)) if error == "assertion failed"
+        ));
+
+        assert_eq!(
+            outcomes.await.unwrap().unwrap(),
+            vec![Outcome::Failure {
+                group: "single".to_owned(),
+                error: "assertion failed".to_owned()
+            }]
+        );
+    }
+
+    #[test]
+    fn build_blocking() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let sync_service = runtime.block_on(MockSyncService::start());
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build_blocking()
+            .unwrap();
+
+        assert_eq!(client.block_on(client.signal_entry("ready")).unwrap(), 1);
+
+        // Drop the last clone within a task of the client's runtime.
+        let (dropped_tx, dropped_rx) = oneshot::channel();
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        let clone = client.clone();
+        client.block_on(async move {
+            tokio::spawn(async move {
+                dropped_rx.await.unwrap();
+                let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(clone)));
+                done_tx.send(res.is_ok()).unwrap();
+            });
+        });
+
+        drop(client);
+        dropped_tx.send(()).unwrap();
+        assert!(done_rx.recv_timeout(Duration::from_secs(5)).unwrap());
+    }
+
+    #[tokio::test]
+    async fn build_blocking_within_runtime() {
+        let sync_service = MockSyncService::start().await;
+
+        let res = builder(&sync_service, test_run_parameters()).build_blocking();
+
+        assert!(matches!(
+            res.err().unwrap().downcast_ref(),
+            Some(InitError::NestedRuntime)
+        ));
+    }
+
+    #[tokio::test]
+    async fn no_sync() {
+        let mut params = test_run_parameters();
+        params.testground_no_sync = true;
+
+        // Not connecting to any sync service.
+        let client = ClientBuilder::new()
+            .run_parameters(params)
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(client.signal_and_wait_all("ready").await.unwrap(), 1);
+        client
+            .publish("peers", Cow::Owned("a".into()))
+            .await
+            .unwrap();
+        let mut stream = client.subscribe("peers", 1).await;
+        assert_eq!(stream.next().await.unwrap().unwrap(), "a");
+    }
+
+    #[tokio::test]
+    async fn await_all_outcomes_collects_mixed_outcomes() {
+        let sync_service = MockSyncService::start().await;
+
+        let mut params = test_run_parameters();
+        params.test_instance_count = 3;
+        params.test_group_instance_count = 3;
+
+        let (a, b, c) = tokio::join!(
+            builder(&sync_service, params.clone()).build(),
+            builder(&sync_service, params.clone()).build(),
+            builder(&sync_service, params.clone()).build(),
+        );
+        let (a, b, c) = (a.unwrap(), b.unwrap(), c.unwrap());
+
+        let supervisor = a.clone();
+        let outcomes = tokio::spawn(async move { supervisor.await_all_outcomes(3).await });
+
+        a.record_success().await.unwrap();
+        b.record_failure("invalid payload").await.unwrap();
+        c.record_crash("panic", "main.rs:1").await.unwrap();
+
+        let outcomes = outcomes.await.unwrap().unwrap();
+
+        assert_eq!(
+            outcomes,
+            vec![
+                Outcome::Success {
+                    group: "single".to_owned()
+                },
+                Outcome::Failure {
+                    group: "single".to_owned(),
+                    error: "invalid payload".to_owned()
+                },
+                Outcome::Crash {
+                    group: "single".to_owned(),
+                    error: "panic".to_owned(),
+                    stacktrace: "main.rs:1".to_owned()
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn outcome_recorded_once_across_clones() {
+        let sync_service = MockSyncService::start().await;
+
+        let a = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+        let (b, c) = (a.clone(), a.clone());
+        assert_eq!(a.clone_count(), 3);
+
+        a.record_success().await.unwrap();
+        assert_eq!(b.clone_count(), 2);
+        assert!(matches!(
+            b.record_failure("late").await,
+            Err(Error::OutcomeAlreadyRecorded)
+        ));
+
+        let d = c.clone();
+        c.close().await.unwrap();
+        assert!(matches!(d.close().await, Err(Error::Closed)));
+    }
+
+    #[tokio::test]
+    async fn requests_fail_once_closed() {
+        let sync_service = MockSyncService::start().await;
+
+        let a = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+        let b = a.clone();
+        a.close().await.unwrap();
+
+        assert!(matches!(b.signal_entry("ready").await, Err(Error::Closed)));
+        assert!(matches!(b.barrier("ready", 1).await, Err(Error::Closed)));
+        assert!(matches!(
+            b.publish("updates", Cow::Owned(serde_json::json!("late")))
+                .await,
+            Err(Error::Closed)
+        ));
+        assert!(matches!(b.await_all_outcomes(1).await, Err(Error::Closed)));
+
+        let mut stream = b.subscribe("updates", 16).await;
+        assert!(matches!(stream.next().await, Some(Err(Error::Closed))));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn barrier_reporting() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let messages = client.stats.messages.load(Ordering::Relaxed);
+
+        let signal = async {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            client.signal_entry("ready").await.unwrap();
+        };
+        let (res, ()) = tokio::join!(
+            client.barrier_reporting("ready", 1, Duration::from_millis(50)),
+            signal
+        );
+        res.unwrap();
+
+        assert!(client.stats.messages.load(Ordering::Relaxed) >= messages + 2);
+    }
+
+    #[tokio::test]
+    async fn failure_guard_records_crash() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let supervisor = client.clone();
+        let outcomes = tokio::spawn(async move { supervisor.await_all_outcomes(1).await });
+
+        // Not recorded without a panic.
+        drop(client.failure_guard());
+
+        let guard = client.failure_guard();
+        let panicked = tokio::task::spawn_blocking(move || {
+            let _guard = guard;
+            panic!("assertion failed");
+        })
+        .await;
+        assert!(panicked.unwrap_err().is_panic());
+
+        let outcomes = outcomes.await.unwrap().unwrap();
+        assert!(matches!(
+            &outcomes[..],
+            [Outcome::Crash { error, stacktrace, .. }]
+                if error == "assertion failed" && stacktrace.contains("client.rs")
+        ));
+        assert!(matches!(
+            client.record_success().await,
+            Err(Error::OutcomeAlreadyRecorded)
+        ));
+    }
+
+    #[tokio::test]
+    async fn failure_guard_records_crash_on_runtime_thread() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let supervisor = client.clone();
+        let outcomes = tokio::spawn(async move { supervisor.await_all_outcomes(1).await });
+
+        // The background task runs on this thread, thus can't acknowledge the
+        // crash before the guard returns.
+        let started = std::time::Instant::now();
+        let guard = client.failure_guard();
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+            let _guard = guard;
+            panic!("assertion failed");
+        }));
+        assert!(panicked.is_err());
+        assert!(started.elapsed() < BLOCKING_CRASH_TIMEOUT);
+
+        let outcomes = outcomes.await.unwrap().unwrap();
+        assert!(matches!(
+            &outcomes[..],
+            [Outcome::Crash { error, .. }] if error == "assertion failed"
+        ));
+    }
+
+    #[tokio::test]
+    async fn panic_hook_records_crash() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let supervisor = client.clone();
+        let outcomes = tokio::spawn(async move { supervisor.await_all_outcomes(1).await });
+
+        // The hook is global, thus may record the panic of another test first.
+        client.install_panic_hook();
+        let started = std::time::Instant::now();
+        assert!(std::panic::catch_unwind(|| panic!("assertion failed")).is_err());
+        assert!(started.elapsed() < BLOCKING_CRASH_TIMEOUT);
+
+        let outcomes = outcomes.await.unwrap().unwrap();
+        assert!(matches!(&outcomes[..], [Outcome::Crash { .. }]));
+        assert!(matches!(
+            client.record_success().await,
+            Err(Error::OutcomeAlreadyRecorded)
+        ));
+    }
+
+    #[test]
+    fn panic_message() {
+        let payload = std::panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(super::panic_message(&*payload), "static");
+
+        let payload = std::panic::catch_unwind(|| panic!("formatted {}", 1)).unwrap_err();
+        assert_eq!(super::panic_message(&*payload), "formatted 1");
+
+        let payload = std::panic::catch_unwind(|| std::panic::panic_any(1)).unwrap_err();
+        assert_eq!(super::panic_message(&*payload), "Box<dyn Any>");
+    }
+
+    #[tokio::test]
+    async fn signal_and_check() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            client.signal_and_check("aggregate", 2).await.unwrap(),
+            (1, false)
+        );
+        assert_eq!(
+            client.signal_and_check("aggregate", 2).await.unwrap(),
+            (2, true)
+        );
+
+        // Only the instance reaching the target is the last one.
+        assert_eq!(
+            client.signal_and_check("aggregate", 2).await.unwrap(),
+            (3, false)
+        );
+    }
+
+    #[tokio::test]
+    async fn command_buffer() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(client.cmd_tx.max_capacity(), 64);
+
+        let client = builder(&sync_service, test_run_parameters())
+            .command_buffer(1)
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(client.cmd_tx.max_capacity(), 1);
+        client.signal_entry("ready").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn concurrent_barriers_complete_out_of_order() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let (fired_tx, mut fired_rx) = mpsc::unbounded_channel();
+        let barriers = ["a", "b", "c"].map(|state| {
+            let (client, fired_tx) = (client.clone(), fired_tx.clone());
+            tokio::spawn(async move {
+                client.barrier(state, 1).await.unwrap();
+                fired_tx.send(state).unwrap();
+            })
+        });
+
+        while sync_service.pending_barriers() < 3 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        for state in ["c", "a", "b"] {
+            client.signal_entry(state).await.unwrap();
+            assert_eq!(fired_rx.recv().await, Some(state));
+        }
+
+        for barrier in barriers {
+            barrier.await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn published_items_visible_to_all_subscriptions() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        let mut early = client.subscribe_indexed("results", 16, true).await;
+
+        let mut seqs = Vec::new();
+        for i in 0..3 {
+            seqs.push(
+                client
+                    .publish("results", Cow::Owned(i.into()))
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        let mut late = client.subscribe_indexed("results", 16, true).await;
+
+        for (i, seq) in seqs.into_iter().enumerate() {
+            let expected = (seq, serde_json::Value::from(i));
+            assert_eq!(early.next().await.unwrap().unwrap(), expected);
+            assert_eq!(late.next().await.unwrap().unwrap(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn ping() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        client.ping().await.unwrap();
+
+        // Only connections idling at the time are closed.
+        while sync_service.connections() > 0 {
+            sync_service.disconnect();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Lost connections are noticed by the background task asynchronously.
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while client.ping().await.is_ok() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("Ping failing once disconnected");
+        // The background task shut down, as it doesn't reconnect.
+        assert!(matches!(client.ping().await, Err(Error::Closed)));
+    }
+
+    #[tokio::test]
+    async fn write_results_csv() {
+        let sync_service = MockSyncService::start().await;
+
+        let mut params = test_run_parameters();
+        params.test_outputs_path = outputs_path("results-csv");
+
+        let client = builder(&sync_service, params.clone())
+            .build()
+            .await
+            .unwrap();
+
+        #[derive(Serialize)]
+        struct Sample {
+            seq: u64,
+            latency_ms: f64,
+        }
+
+        let rows = (1..=2).map(|seq| Sample {
+            seq,
+            latency_ms: seq as f64 * 1.5,
+        });
+        client.write_results_csv("latencies.csv", rows).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(params.test_outputs_path.join("latencies.csv")).unwrap(),
+            "seq,latency_ms\n1,1.5\n2,3.0\n"
+        );
+
+        std::fs::remove_dir_all(params.test_outputs_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn subscribe_filter() {
+        let sync_service = MockSyncService::start().await;
+
+        let client = builder(&sync_service, test_run_parameters())
+            .build()
+            .await
+            .unwrap();
+
+        for to in [1, 2, 1] {
+            client
+                .publish("messages", Cow::Owned(serde_json::json!({ "to": to })))
+                .await
+                .unwrap();
+        }
+
+        let stream = client
+            .subscribe_filter("messages", 16, |item| item["to"] == 1)
+            .await;
+        let items: Vec<_> = stream.take(2).map(Result::unwrap).collect().await;
+
+        assert_eq!(items, vec![serde_json::json!({ "to": 1 }); 2]);
+    }
+}
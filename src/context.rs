@@ -0,0 +1,63 @@
+//! Contextualization of states and topics with the run, plan and case, i.e.
+//! the keys under which the sync service stores them.
+
+use crate::RunParameters;
+
+/// Contextualizes a state with the run, plan and case, but not the group,
+/// thus states are shared by all groups of a run.
+pub fn state(params: &RunParameters, state: &str) -> String {
+    format!(
+        "run:{}:plan:{}:case:{}:states:{}",
+        params.test_run, params.test_plan, params.test_case, state
+    )
+}
+
+pub fn topic(params: &RunParameters, topic: &str) -> String {
+    format!(
+        "run:{}:plan:{}:case:{}:topics:{}",
+        params.test_run, params.test_plan, params.test_case, topic
+    )
+}
+
+pub fn group_topic(params: &RunParameters, topic: &str) -> String {
+    format!(
+        "run:{}:plan:{}:case:{}:groups:{}:topics:{}",
+        params.test_run, params.test_plan, params.test_case, params.test_group_id, topic
+    )
+}
+
+/// The topic of the run events, e.g. the outcomes of all instances.
+pub fn events(params: &RunParameters) -> String {
+    format!(
+        "run:{}:plan:{}:case:{}:run_events",
+        params.test_run, params.test_plan, params.test_case
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::test_run_parameters;
+
+    #[test]
+    fn contextualize() {
+        let params = test_run_parameters();
+
+        assert_eq!(
+            state(&params, "ready"),
+            "run:c7fjstge5te621cen4i0:plan:sdk-rust:case:example:states:ready"
+        );
+        assert_eq!(
+            topic(&params, "peers"),
+            "run:c7fjstge5te621cen4i0:plan:sdk-rust:case:example:topics:peers"
+        );
+        assert_eq!(
+            group_topic(&params, "peers"),
+            "run:c7fjstge5te621cen4i0:plan:sdk-rust:case:example:groups:single:topics:peers"
+        );
+        assert_eq!(
+            events(&params),
+            "run:c7fjstge5te621cen4i0:plan:sdk-rust:case:example:run_events"
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! A minimal CSV writer for rows of flat structs, see
+//! [`Client::write_results_csv`](crate::client::Client::write_results_csv).
+
+use std::io::Write;
+
+use serde::ser::{self, Impossible, Serialize, Serializer};
+
+use crate::errors::Error;
+
+/// Writes `rows` as CSV, with a header naming the fields of the first row.
+///
+/// Rows must be structs of scalar fields, e.g. numbers or strings, with the
+/// same fields. Other fields, e.g. vectors, are written as JSON. Writes
+/// nothing if there are no rows.
+pub fn write<W: Write, T: Serialize>(
+    mut writer: W,
+    rows: impl IntoIterator<Item = T>,
+) -> Result<(), Error> {
+    let mut header: Option<Vec<&'static str>> = None;
+
+    for row in rows {
+        let row = row.serialize(RowSerializer).map_err(|e| Error::Csv(e.0))?;
+
+        match &header {
+            None => {
+                write_record(&mut writer, row.names.iter().copied())?;
+                header = Some(row.names);
+            }
+            Some(header) if *header != row.names => {
+                return Err(Error::Csv(format!(
+                    "row with fields {:?} following rows with fields {:?}",
+                    row.names, header
+                )));
+            }
+            Some(_) => {}
+        }
+
+        write_record(&mut writer, row.values.iter().map(String::as_str))?;
+    }
+
+    Ok(())
+}
+
+fn write_record<'a, W: Write>(
+    writer: &mut W,
+    cells: impl Iterator<Item = &'a str>,
+) -> std::io::Result<()> {
+    for (i, cell) in cells.enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+
+        // Quote cells as per RFC 4180.
+        if cell.contains([',', '"', '\n', '\r']) {
+            write!(writer, "\"{}\"", cell.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(cell.as_bytes())?;
+        }
+    }
+
+    writer.write_all(b"\n")
+}
+
+/// The cell of a field, e.g. `42` or `peer-a`, empty for `None`.
+fn cell<T: Serialize + ?Sized>(value: &T) -> Result<String, CsvError> {
+    match serde_json::to_value(value).map_err(ser::Error::custom)? {
+        serde_json::Value::Null => Ok(String::new()),
+        serde_json::Value::String(value) => Ok(value),
+        value => Ok(value.to_string()),
+    }
+}
+
+#[derive(Debug)]
+struct CsvError(String);
+
+impl std::fmt::Display for CsvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+impl ser::Error for CsvError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        CsvError(msg.to_string())
+    }
+}
+
+struct Row {
+    names: Vec<&'static str>,
+    values: Vec<String>,
+}
+
+impl ser::SerializeStruct for Row {
+    type Ok = Row;
+    type Error = CsvError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), CsvError> {
+        self.names.push(key);
+        self.values.push(cell(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Row, CsvError> {
+        Ok(self)
+    }
+}
+
+/// Serializes a struct into a [`Row`], rejecting anything else.
+struct RowSerializer;
+
+macro_rules! unsupported {
+    ($($method:ident($($arg:ty),*) -> $ok:ty;)+) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ok, CsvError> {
+                Err(CsvError("rows must be structs".to_owned()))
+            }
+        )+
+    };
+}
+
+impl Serializer for RowSerializer {
+    type Ok = Row;
+    type Error = CsvError;
+    type SerializeSeq = Impossible<Row, CsvError>;
+    type SerializeTuple = Impossible<Row, CsvError>;
+    type SerializeTupleStruct = Impossible<Row, CsvError>;
+    type SerializeTupleVariant = Impossible<Row, CsvError>;
+    type SerializeMap = Impossible<Row, CsvError>;
+    type SerializeStruct = Row;
+    type SerializeStructVariant = Impossible<Row, CsvError>;
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Row, CsvError> {
+        Ok(Row {
+            names: Vec::with_capacity(len),
+            values: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Row, CsvError> {
+        value.serialize(self)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<Row, CsvError> {
+        Err(CsvError("rows must be structs".to_owned()))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Row, CsvError> {
+        Err(CsvError("rows must be structs".to_owned()))
+    }
+
+    unsupported! {
+        serialize_bool(bool) -> Row;
+        serialize_i8(i8) -> Row;
+        serialize_i16(i16) -> Row;
+        serialize_i32(i32) -> Row;
+        serialize_i64(i64) -> Row;
+        serialize_u8(u8) -> Row;
+        serialize_u16(u16) -> Row;
+        serialize_u32(u32) -> Row;
+        serialize_u64(u64) -> Row;
+        serialize_f32(f32) -> Row;
+        serialize_f64(f64) -> Row;
+        serialize_char(char) -> Row;
+        serialize_str(&str) -> Row;
+        serialize_bytes(&[u8]) -> Row;
+        serialize_none() -> Row;
+        serialize_unit() -> Row;
+        serialize_unit_struct(&'static str) -> Row;
+        serialize_unit_variant(&'static str, u32, &'static str) -> Row;
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Sample {
+        peer: &'static str,
+        latency_ms: f64,
+        retries: Option<u32>,
+    }
+
+    #[test]
+    fn write_rows() {
+        let rows = [
+            Sample {
+                peer: "a",
+                latency_ms: 1.5,
+                retries: Some(2),
+            },
+            Sample {
+                peer: "b, \"c\"",
+                latency_ms: 20.0,
+                retries: None,
+            },
+        ];
+
+        let mut csv = Vec::new();
+        write(&mut csv, &rows).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "peer,latency_ms,retries\na,1.5,2\n\"b, \"\"c\"\"\",20.0,\n"
+        );
+
+        assert!(matches!(write(Vec::new(), [1, 2]), Err(Error::Csv(_))));
+    }
+}
@@ -1,17 +1,100 @@
+use std::time::Duration;
+
 use thiserror::Error;
 
+use crate::RunnerKind;
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Soketto: {0}")]
     Soketto(#[from] soketto::connection::Error),
+    #[error("Request not sent, reconnected to the sync service: {0}")]
+    NotSent(soketto::connection::Error),
     #[error("Serde: {0}")]
     Serde(#[from] serde_json::error::Error),
+    #[error("Serialize: {0}")]
+    Serialize(serde_json::error::Error),
+    #[error("Base64: {0}")]
+    Base64(#[from] base64::DecodeError),
     #[error("UTF-8: {0}")]
     FromUtf8(#[from] std::string::FromUtf8Error),
     #[error("Sync-Service: {0}")]
-    SyncService(String),
-    #[error("The SideCar is not running")]
-    SideCar,
+    SyncService(SyncServiceError),
+    #[error("Malformed sync service response: {0}")]
+    MalformedResponse(serde_json::Value),
+    #[error("Disconnected from the sync service")]
+    Disconnected,
+    #[error("The connection to the sync service was closed by a clone of the client")]
+    Closed,
+    #[error("The outcome of the instance was already recorded by a clone of the client")]
+    OutcomeAlreadyRecorded,
+    #[error("Test failure: {0}")]
+    Failure(String),
+    #[error("Not supported on the {0} runner")]
+    UnsupportedOnRunner(RunnerKind),
+    #[cfg(feature = "metrics")]
     #[error("InfluxDB: {0}")]
     InfluxDB(#[from] influxdb::Error),
+    #[cfg(feature = "metrics")]
+    #[error("Invalid InfluxDB URL: {0:?}")]
+    InvalidInfluxUrl(String),
+    #[cfg(feature = "metrics")]
+    #[error("InfluxDB: Point {index} of the batch: {source}")]
+    InvalidMetric {
+        index: usize,
+        source: influxdb::Error,
+    },
+    #[error("Sequence gap: expected {expected}, got {got}")]
+    SequenceGap { expected: u64, got: u64 },
+    #[error("No item received within {0:?}")]
+    IdleTimeout(Duration),
+    #[error("Reassembly: {0}")]
+    Reassembly(String),
+    #[error("IO: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("CSV: {0}")]
+    Csv(String),
+    #[error("Barrier on {state} did not reach {target} within {waited:?}")]
+    Timeout {
+        state: String,
+        target: u64,
+        waited: Duration,
+    },
+}
+
+/// Errors occurring while initializing a [`Client`](crate::client::Client).
+#[derive(Error, Debug)]
+pub enum InitError {
+    #[error("Sync service handshake timed out after {0:?}")]
+    HandshakeTimeout(Duration),
+    #[error("Cannot block on the initialization from within an async runtime")]
+    NestedRuntime,
+}
+
+/// Errors occurring while reading the
+/// [`RunParameters`](crate::RunParameters) from the environment, see
+/// [`RunParameters::from_env`](crate::RunParameters::from_env).
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParamsError {
+    #[error("{0} not set")]
+    Missing(&'static str),
+    #[error("{name} not a {expected}: {value:?}")]
+    Malformed {
+        name: &'static str,
+        expected: &'static str,
+        value: String,
+    },
+    #[error("TEST_SUBNET not a valid subnet ({reason}): {value:?}")]
+    InvalidSubnet { value: String, reason: String },
+}
+
+/// An error returned by the sync service.
+///
+/// Errors carrying a code, i.e. sent as `{"code": ..., "message": ...}`, can be
+/// told apart by their `code`. Any other error only has a `message`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{}{message}", code.as_ref().map(|c| format!("{c}: ")).unwrap_or_default())]
+pub struct SyncServiceError {
+    pub code: Option<String>,
+    pub message: String,
 }
@@ -1,26 +1,41 @@
 #![allow(dead_code)]
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::RunParameters;
+
 #[derive(Serialize, Debug)]
 pub struct Event {
     pub event: EventType,
 }
 
+/// An event as logged to stdout and `run.out`, attributed to the instance.
+///
+/// The Testground daemon only parses the `event`, ignoring other fields.
 #[derive(Debug, Serialize)]
 pub struct LogLine<'a> {
     pub ts: u128,
+    pub hostname: &'a str,
+    /// The global sequence number of the instance, once assigned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_seq: Option<u64>,
     pub event: &'a EventType,
 }
 
-impl LogLine<'_> {
-    pub fn new(event: &EventType) -> LogLine {
+impl<'a> LogLine<'a> {
+    pub fn new(
+        event: &'a EventType,
+        params: &'a RunParameters,
+        instance_seq: Option<u64>,
+    ) -> LogLine<'a> {
         LogLine {
             ts: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_nanos(),
+            hostname: &params.hostname,
+            instance_seq,
             event,
         }
     }
@@ -48,6 +63,22 @@ pub enum EventType {
     StageEnd { name: String, group: String },
 }
 
+/// The terminal state of a test instance, as recorded on the run events topic.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    #[serde(rename = "success_event")]
+    Success { group: String },
+    #[serde(rename = "failure_event")]
+    Failure { group: String, error: String },
+    #[serde(rename = "crash_event")]
+    Crash {
+        #[serde(rename = "groups")]
+        group: String,
+        error: String,
+        stacktrace: String,
+    },
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -67,4 +98,53 @@ mod tests {
 
         println!("{:?}", json);
     }
+
+    #[test]
+    fn log_line() {
+        let params = crate::params::test_run_parameters();
+        let event = EventType::Message {
+            message: "hello".to_owned(),
+        };
+
+        let mut line = serde_json::to_value(LogLine::new(&event, &params, None)).unwrap();
+        line.as_object_mut().unwrap().remove("ts");
+        assert_eq!(
+            line,
+            serde_json::json!({
+                "hostname": "e6f4cc8fc147",
+                "event": {"message_event": {"message": "hello"}},
+            })
+        );
+
+        let line = serde_json::to_value(LogLine::new(&event, &params, Some(2))).unwrap();
+        assert_eq!(line["instance_seq"], 2);
+    }
+
+    #[test]
+    fn outcome_from_event() {
+        let event = EventType::Crash {
+            groups: "single".to_owned(),
+            error: "boom".to_owned(),
+            stacktrace: "main.rs:1".to_owned(),
+        };
+
+        let outcome: Outcome =
+            serde_json::from_value(serde_json::to_value(&event).unwrap()).unwrap();
+
+        assert_eq!(
+            outcome,
+            Outcome::Crash {
+                group: "single".to_owned(),
+                error: "boom".to_owned(),
+                stacktrace: "main.rs:1".to_owned(),
+            }
+        );
+
+        let event = EventType::StageEnd {
+            name: "network-initialized".to_owned(),
+            group: "single".to_owned(),
+        };
+
+        assert!(serde_json::from_value::<Outcome>(serde_json::to_value(&event).unwrap()).is_err());
+    }
 }
@@ -1,13 +1,28 @@
 mod background;
+mod chunking;
 pub mod client;
+mod context;
+mod csv;
 pub mod errors;
 mod events;
+mod local;
+pub mod logger;
+pub mod metrics;
 pub mod network_conf;
 mod params;
 mod requests;
 mod responses;
+mod run_out;
+pub mod subscription;
+mod summary;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
-pub use params::RunParameters;
+pub use events::Outcome;
+pub use params::{ProfileKind, ProfileSpec, RunParameters, RunnerKind};
 
 // Re-export public dependencies.
+#[cfg(feature = "metrics")]
 pub use influxdb::{Timestamp, WriteQuery};
+#[cfg(not(feature = "metrics"))]
+pub use metrics::{Timestamp, WriteQuery};
@@ -0,0 +1,216 @@
+//! An in-process sync service speaking the websocket protocol of the Testground
+//! sync service, allowing to run a test plan without a Testground daemon, see
+//! `TESTGROUND_NO_SYNC`.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+use soketto::handshake::{server::Response, Server};
+use tokio::io::{AsyncRead, AsyncWrite, DuplexStream};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::sync::Notify;
+use tokio_util::compat::TokioAsyncReadCompatExt;
+
+/// The buffer size of an in-process connection.
+const BUFFER: usize = 64 * 1024;
+
+/// A sync service keeping its states and topics in memory.
+///
+/// Every connection shares the same states and topics, thus multiple clients
+/// connected to the same service behave like instances of a single run.
+#[derive(Clone, Default)]
+pub struct LocalSyncService {
+    pub(crate) state: Arc<Mutex<State>>,
+    pub(crate) disconnect: Arc<Notify>,
+}
+
+type Connection = UnboundedSender<Vec<u8>>;
+
+#[derive(Default)]
+pub(crate) struct State {
+    states: HashMap<String, u64>,
+    pub(crate) connections: usize,
+    pub(crate) barriers: Vec<(Connection, String, String, u64)>,
+    pub(crate) topics: HashMap<String, Vec<Value>>,
+    pub(crate) subscriptions: Vec<(Connection, String, String)>,
+}
+
+impl LocalSyncService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens an in-process connection, returning the client's end of it.
+    pub fn connect(&self) -> DuplexStream {
+        let (client, server) = tokio::io::duplex(BUFFER);
+
+        self.serve(server);
+
+        client
+    }
+
+    /// Serves the websocket connection of a client over `socket`.
+    pub fn serve<S>(&self, socket: S)
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        tokio::spawn(connection(
+            socket,
+            self.state.clone(),
+            self.disconnect.clone(),
+        ));
+    }
+}
+
+async fn connection<S>(socket: S, state: Arc<Mutex<State>>, disconnect: Arc<Notify>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let mut server = Server::new(socket.compat());
+
+    let key = match server.receive_request().await {
+        Ok(req) => req.key(),
+        Err(_) => return,
+    };
+
+    let accept = Response::Accept {
+        key,
+        protocol: None,
+    };
+    if server.send_response(&accept).await.is_err() {
+        return;
+    }
+
+    let (mut sender, mut receiver) = server.into_builder().finish();
+
+    state.lock().unwrap().connections += 1;
+
+    let (conn, mut responses) = mpsc::unbounded_channel::<Vec<u8>>();
+
+    let responder = tokio::spawn(async move {
+        while let Some(res) = responses.recv().await {
+            if sender
+                .send_text(String::from_utf8(res).unwrap())
+                .await
+                .is_err()
+                || sender.flush().await.is_err()
+            {
+                return;
+            }
+        }
+    });
+
+    loop {
+        let mut buf = Vec::new();
+        let received = tokio::select! {
+            received = receiver.receive_data(&mut buf) => received.is_ok(),
+            _ = disconnect.notified() => false,
+        };
+
+        if !received {
+            // Dropping both halves closes the socket.
+            responder.abort();
+            state.lock().unwrap().connections -= 1;
+            return;
+        }
+
+        let req: Value = match serde_json::from_slice(&buf) {
+            Ok(req) => req,
+            Err(e) => {
+                log::error!("Ignoring malformed request: {}", e);
+                continue;
+            }
+        };
+
+        state.lock().unwrap().request(&conn, req);
+    }
+}
+
+impl State {
+    fn request(&mut self, conn: &Connection, req: Value) {
+        let id = req["id"].as_str().expect("Request ID").to_owned();
+
+        if req["is_cancel"].as_bool().unwrap_or_default() {
+            self.barriers
+                .retain(|(other, other_id, _, _)| !(other.same_channel(conn) && *other_id == id));
+            self.subscriptions
+                .retain(|(other, other_id, _)| !(other.same_channel(conn) && *other_id == id));
+        } else if let Some(signal) = req.get("signal_entry") {
+            let state = signal["state"].as_str().unwrap().to_owned();
+
+            let seq = self.states.entry(state.clone()).or_default();
+            *seq += 1;
+            let seq = *seq;
+
+            respond(conn, json!({ "id": id, "signal_entry": { "seq": seq } }));
+
+            self.barriers.retain(|(conn, id, barrier, target)| {
+                if *barrier == state && seq >= *target {
+                    respond(conn, json!({ "id": id }));
+                    false
+                } else {
+                    true
+                }
+            });
+        } else if let Some(barrier) = req.get("barrier") {
+            let state = barrier["state"].as_str().unwrap().to_owned();
+            let target = barrier["target"].as_u64().unwrap();
+
+            if self.states.get(&state).copied().unwrap_or_default() >= target {
+                respond(conn, json!({ "id": id }));
+            } else {
+                self.barriers.push((conn.clone(), id, state, target));
+            }
+        } else if let Some(state_count) = req.get("state_count") {
+            let state = state_count["state"].as_str().unwrap();
+            let count = self.states.get(state).copied().unwrap_or_default();
+
+            respond(conn, json!({ "id": id, "state_count": { "count": count } }));
+        } else if let Some(publish) = req.get("publish") {
+            let topic = publish["topic"].as_str().unwrap().to_owned();
+            let payload = publish["payload"].clone();
+
+            let items = self.topics.entry(topic.clone()).or_default();
+            items.push(payload.clone());
+            let seq = items.len();
+
+            respond(conn, json!({ "id": id, "publish": { "seq": seq } }));
+
+            for (conn, id, subscription) in self.subscriptions.iter() {
+                if *subscription == topic {
+                    respond(conn, subscribe_response(id, &payload));
+                }
+            }
+        } else if let Some(subscribe) = req.get("subscribe") {
+            let topic = subscribe["topic"].as_str().unwrap().to_owned();
+
+            for payload in self.topics.get(&topic).into_iter().flatten() {
+                respond(conn, subscribe_response(&id, payload));
+            }
+
+            self.subscriptions.push((conn.clone(), id, topic));
+        } else {
+            log::error!("Ignoring unknown request {}", req);
+        }
+    }
+}
+
+fn subscribe_response(id: &str, payload: &Value) -> Value {
+    // Like the sync service, the payload is sent as a JSON encoded string.
+    json!({ "id": id, "subscribe": payload.to_string() })
+}
+
+fn respond(conn: &Connection, mut res: Value) {
+    // Like the sync service, always include the `error` and `subscribe` fields.
+    for field in ["error", "subscribe"] {
+        res.as_object_mut()
+            .unwrap()
+            .entry(field)
+            .or_insert_with(|| "".into());
+    }
+
+    // The connection may already be gone, in which case there is no one left to
+    // respond to.
+    let _ = conn.send(serde_json::to_vec(&res).expect("Response Serialization"));
+}
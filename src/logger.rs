@@ -0,0 +1,45 @@
+//! A [`log`] facade logger, recording log records as messages of the test run.
+
+use std::io::Write;
+
+use log::{Log, Metadata, Record};
+
+use crate::client::Client;
+
+/// Records every log record as a message, see [`Client::record_message`],
+/// thus logs of the plan and its dependencies show up in the Testground output.
+///
+/// Installed by [`Client::init_logger`]. Records are filtered by
+/// [`log::max_level`].
+pub struct TestgroundLogger {
+    client: Client,
+}
+
+impl TestgroundLogger {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+impl Log for TestgroundLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        self.client.record_message(format!(
+            "{} [{}] {}",
+            record.level(),
+            record.target(),
+            record.args()
+        ));
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stdout().flush();
+    }
+}
@@ -0,0 +1,367 @@
+#[cfg(feature = "metrics")]
+use influxdb::{Timestamp, WriteQuery};
+
+#[cfg(not(feature = "metrics"))]
+pub use noop::{Timestamp, WriteQuery, WriteType};
+
+/// Prefix of the measurements of results metrics, i.e. the outcome of a
+/// test such as latencies or throughput, recorded by
+/// [`Client::record_result_metric`](crate::client::Client::record_result_metric),
+/// [`Client::record_counter`](crate::client::Client::record_counter),
+/// [`Client::record_gauge`](crate::client::Client::record_gauge) and
+/// [`Client::record_point`](crate::client::Client::record_point), like the
+/// results metrics of the Go SDK.
+pub const RESULTS_PREFIX: &str = "results";
+
+/// Prefix of the measurements of diagnostics metrics, i.e. insights into the
+/// behavior of a test instance such as its memory usage, recorded by
+/// [`Client::record_diagnostic_metric`](crate::client::Client::record_diagnostic_metric),
+/// like the diagnostics metrics of the Go SDK.
+pub const DIAGNOSTICS_PREFIX: &str = "diagnostics";
+
+/// Histogram accumulates samples in-process and summarizes them into a single
+/// point once flushed.
+///
+/// The emitted point carries the `count`, `min`, `max`, `mean`, `p50`, `p90`
+/// and `p99` fields under the histogram's measurement, so that plans recording
+/// distributions (e.g. latencies) don't have to ship every raw sample to
+/// InfluxDB.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    measurement: String,
+    tags: Vec<(String, String)>,
+    samples: Vec<f64>,
+}
+
+impl Histogram {
+    pub fn new(measurement: impl Into<String>) -> Self {
+        Self {
+            measurement: measurement.into(),
+            tags: Vec::new(),
+            samples: Vec::new(),
+        }
+    }
+
+    /// Adds a tag to every point emitted by this histogram.
+    pub fn add_tag(mut self, tag: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push((tag.into(), value.into()));
+        self
+    }
+
+    /// Records a single sample.
+    pub fn record(&mut self, sample: f64) {
+        self.samples.push(sample);
+    }
+
+    /// Returns the number of samples recorded since the last flush.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Summarizes the recorded samples into a [`WriteQuery`] and resets the
+    /// histogram.
+    ///
+    /// Returns `None` if no sample was recorded since the last flush.
+    pub fn flush(&mut self, timestamp: Timestamp) -> Option<WriteQuery> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        let mut samples = std::mem::take(&mut self.samples);
+        samples.sort_by(|a, b| a.total_cmp(b));
+
+        let count = samples.len();
+        let mean = samples.iter().sum::<f64>() / count as f64;
+
+        let mut query = WriteQuery::new(timestamp, self.measurement.clone())
+            .add_field("count", count as u64)
+            .add_field("min", samples[0])
+            .add_field("max", samples[count - 1])
+            .add_field("mean", mean)
+            .add_field("p50", percentile(&samples, 50.0))
+            .add_field("p90", percentile(&samples, 90.0))
+            .add_field("p99", percentile(&samples, 99.0));
+
+        for (tag, value) in self.tags.iter() {
+            query = query.add_tag(tag.clone(), value.clone());
+        }
+
+        Some(query)
+    }
+}
+
+/// A metric point, converting into a [`WriteQuery`] for
+/// [`Client::record_metric`](crate::client::Client::record_metric), without
+/// having to know the InfluxDB API.
+///
+/// ```
+/// # use testground::metrics::Point;
+/// let point = Point::new("latency").tag("peer", "a").field("ms", 42.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Point {
+    measurement: String,
+    timestamp: Option<Timestamp>,
+    tags: Vec<(String, String)>,
+    fields: Vec<(String, FieldValue)>,
+}
+
+/// The value of a field of a [`Point`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Boolean(bool),
+    Float(f64),
+    SignedInteger(i64),
+    UnsignedInteger(u64),
+    Text(String),
+}
+
+macro_rules! field_value {
+    ($($variant:ident($typ:ty) <= $($from:ty),+);+ $(;)?) => {
+        $($(
+            impl From<$from> for FieldValue {
+                fn from(value: $from) -> Self {
+                    FieldValue::$variant(<$typ>::from(value))
+                }
+            }
+        )+)+
+    };
+}
+
+field_value! {
+    Boolean(bool) <= bool;
+    Float(f64) <= f32, f64;
+    SignedInteger(i64) <= i8, i16, i32, i64;
+    UnsignedInteger(u64) <= u8, u16, u32, u64;
+    Text(String) <= String, &str;
+}
+
+impl Point {
+    pub fn new(measurement: impl Into<String>) -> Self {
+        Self {
+            measurement: measurement.into(),
+            timestamp: None,
+            tags: Vec::new(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push((tag.into(), value.into()));
+        self
+    }
+
+    pub fn field(mut self, field: impl Into<String>, value: impl Into<FieldValue>) -> Self {
+        self.fields.push((field.into(), value.into()));
+        self
+    }
+
+    /// Sets the timestamp of the point, defaulting to the time of its
+    /// conversion into a [`WriteQuery`].
+    pub fn timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Prepends `prefix` to the measurement, e.g. `results.latency`.
+    pub(crate) fn prefixed(mut self, prefix: &str) -> Self {
+        self.measurement = format!("{}.{}", prefix, self.measurement);
+        self
+    }
+}
+
+/// A point of the `results.<name>` measurement, carrying `fields`.
+pub(crate) fn result_point(
+    name: &str,
+    fields: impl IntoIterator<Item = (String, FieldValue)>,
+) -> Point {
+    let mut point = Point::new(name).prefixed(RESULTS_PREFIX);
+    point.fields.extend(fields);
+    point
+}
+
+/// The current time, in nanoseconds since the Unix epoch, see
+/// [`Client::now_timestamp`](crate::client::Client::now_timestamp).
+pub(crate) fn now() -> Timestamp {
+    Timestamp::Nanoseconds(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos(),
+    )
+}
+
+impl From<Point> for WriteQuery {
+    fn from(point: Point) -> Self {
+        let timestamp = point.timestamp.unwrap_or_else(now);
+
+        let mut query = WriteQuery::new(timestamp, point.measurement);
+
+        for (tag, value) in point.tags {
+            query = query.add_tag(tag, value);
+        }
+
+        for (field, value) in point.fields {
+            query = match value {
+                FieldValue::Boolean(value) => query.add_field(field, value),
+                FieldValue::Float(value) => query.add_field(field, value),
+                FieldValue::SignedInteger(value) => query.add_field(field, value),
+                FieldValue::UnsignedInteger(value) => query.add_field(field, value),
+                FieldValue::Text(value) => query.add_field(field, value),
+            };
+        }
+
+        query
+    }
+}
+
+/// Nearest-rank percentile of an already sorted, non-empty slice.
+fn percentile(sorted: &[f64], percentile: f64) -> f64 {
+    let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Stand-ins for the InfluxDB types when the `metrics` feature is disabled,
+/// allowing plans to record metrics irrespective of the feature.
+#[cfg(not(feature = "metrics"))]
+mod noop {
+    /// See [`influxdb::Timestamp`](https://docs.rs/influxdb/0.5/influxdb/enum.Timestamp.html).
+    #[derive(PartialEq, Eq, Debug, Copy, Clone)]
+    pub enum Timestamp {
+        Nanoseconds(u128),
+        Microseconds(u128),
+        Milliseconds(u128),
+        Seconds(u128),
+        Minutes(u128),
+        Hours(u128),
+    }
+
+    /// See [`influxdb::WriteQuery`](https://docs.rs/influxdb/0.5/influxdb/struct.WriteQuery.html).
+    ///
+    /// Fields and tags are discarded, as metrics are never written.
+    #[derive(Debug, Clone)]
+    pub struct WriteQuery(());
+
+    impl WriteQuery {
+        pub fn new<S>(_timestamp: Timestamp, _measurement: S) -> Self
+        where
+            S: Into<String>,
+        {
+            Self(())
+        }
+
+        pub fn add_field<S, F>(self, _field: S, _value: F) -> Self
+        where
+            S: Into<String>,
+            F: WriteType,
+        {
+            self
+        }
+
+        pub fn add_tag<S, I>(self, _tag: S, _value: I) -> Self
+        where
+            S: Into<String>,
+            I: WriteType,
+        {
+            self
+        }
+    }
+
+    /// Values accepted as fields and tags of a [`WriteQuery`].
+    pub trait WriteType {}
+
+    impl<T: WriteType> WriteType for Option<T> {}
+    impl<T: Copy + WriteType> WriteType for &T {}
+
+    macro_rules! write_type {
+        ($($typ:ty),+) => { $(impl WriteType for $typ {})+ };
+    }
+
+    write_type!(bool, f32, f64, i8, i16, i32, i64, u8, u16, u32, u64, String, &str);
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use influxdb::Query;
+
+    use super::*;
+
+    #[test]
+    fn histogram_flush() {
+        let mut histogram = Histogram::new("latency").add_tag("peer", "a");
+
+        // Record out of order to make sure samples are sorted before summarizing.
+        for sample in (1..=100).rev() {
+            histogram.record(sample as f64);
+        }
+
+        let query = histogram
+            .flush(Timestamp::Nanoseconds(0))
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            query,
+            "latency,peer=a count=100i,min=1,max=100,mean=50.5,p50=50,p90=90,p99=99 0"
+        );
+
+        assert!(histogram.is_empty());
+        assert!(histogram.flush(Timestamp::Nanoseconds(0)).is_none());
+    }
+
+    #[test]
+    fn result_point_measurement() {
+        let point = result_point("latency", [("value".to_owned(), 42.0.into())])
+            .timestamp(Timestamp::Nanoseconds(0));
+
+        let query = WriteQuery::from(point).build().unwrap();
+
+        assert_eq!(query.get(), "results.latency value=42 0");
+    }
+
+    #[test]
+    fn diagnostics_point_measurement() {
+        let point = Point::new("memory")
+            .field("bytes", 1024u64)
+            .timestamp(Timestamp::Nanoseconds(0))
+            .prefixed(DIAGNOSTICS_PREFIX);
+
+        let query = WriteQuery::from(point).build().unwrap();
+
+        assert_eq!(query.get(), "diagnostics.memory bytes=1024i 0");
+    }
+
+    #[test]
+    fn now_in_nanoseconds() {
+        let Timestamp::Nanoseconds(now) = now() else {
+            panic!("Timestamp in nanoseconds");
+        };
+
+        // Later than 2022 in nanoseconds, rather than in a coarser unit.
+        assert!(now > 1_640_995_200_000_000_000);
+    }
+
+    #[test]
+    fn point_into_write_query() {
+        let point = Point::new("transfer")
+            .tag("peer", "a")
+            .field("bytes", 1024u64)
+            .field("rate", 0.5)
+            .field("ok", true)
+            .field("protocol", "tcp")
+            .timestamp(Timestamp::Nanoseconds(0));
+
+        let query = WriteQuery::from(point).build().unwrap();
+
+        assert_eq!(
+            query.get(),
+            "transfer,peer=a bytes=1024i,rate=0.5,ok=true,protocol=\"tcp\" 0"
+        );
+    }
+}
@@ -1,15 +1,27 @@
 #![allow(dead_code)]
 
+use std::time::Duration;
+
 use ipnetwork::{IpNetwork, Ipv4Network, Ipv6Network};
-use serde::Serialize;
+use serde::{Serialize, Serializer};
 
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
+mod presets;
+
+/// FilterAction defines how the sidecar filters inbound traffic of a link.
+///
+/// Serialized as the integer values of the Go SDK's `FilterAction` enum, i.e.
+/// `0`, `1` and `2`.
 #[derive(Serialize_repr, Deserialize_repr, PartialEq, Eq, Debug)]
 #[repr(u8)]
 pub enum FilterAction {
+    /// Accept all inbound traffic.
     Accept = 0,
+    /// Reject inbound traffic, notifying the sender, e.g. with a `connection
+    /// refused`.
     Reject = 1,
+    /// Silently drop all inbound traffic, e.g. to partition instances.
     Drop = 2,
 }
 
@@ -17,16 +29,18 @@ pub enum FilterAction {
 /// LinkShape defines how traffic should be shaped.
 pub struct LinkShape {
     /// Latency is the egress latency.
-    pub latency: u64,
+    #[serde(serialize_with = "nanos")]
+    pub latency: Duration,
 
     /// Jitter is the egress jitter.
-    pub jitter: u64,
+    #[serde(serialize_with = "nanos")]
+    pub jitter: Duration,
 
     /// Bandwidth is egress bits per second.
     pub bandwidth: u64,
 
-    /// Drop all inbound traffic.
-    /// TODO: Not implemented
+    /// Filter applied to inbound traffic, e.g. [`FilterAction::Drop`] set on
+    /// the rule of a subnet partitions this instance from the subnet.
     pub filter: FilterAction,
 
     /// Loss is the egress packet loss (%)
@@ -55,8 +69,17 @@ pub struct LinkShape {
     pub duplicate_corr: f32,
 }
 
+/// Serializes a [`Duration`] as integer nanoseconds, like Go's `time.Duration`.
+fn nanos<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_nanos().try_into().unwrap_or(u64::MAX))
+}
+
 #[derive(Serialize, Debug)]
 /// LinkRule applies a LinkShape to a subnet.
+///
+/// Serialized as the fields of the LinkShape along with the `subnet`, e.g.
+/// `{"latency":200000000,...,"subnet":"16.0.1.0/24"}`, like the embedded
+/// struct of the Go SDK.
 pub struct LinkRule {
     #[serde(flatten)]
     pub link_shape: LinkShape,
@@ -66,6 +89,12 @@ pub struct LinkRule {
 
 pub const DEFAULT_DATA_NETWORK: &str = "default";
 
+/// RoutingPolicyType defines whether an instance can reach networks other than
+/// its data networks, e.g. the Internet.
+///
+/// The sidecar doesn't support a finer policy, e.g. allowing specific subnets,
+/// see [`NetworkConfiguration::rules`] to shape the traffic to a subnet
+/// instead.
 #[derive(Serialize, Debug)]
 pub enum RoutingPolicyType {
     #[serde(rename = "allow_all")]
@@ -77,7 +106,12 @@ pub enum RoutingPolicyType {
 /// NetworkConfiguration specifies how a node's network should be configured.
 #[derive(Serialize, Debug)]
 pub struct NetworkConfiguration {
-    /// Network is the name of the network to configure.
+    /// Network is the name of the network to configure, e.g.
+    /// [`DEFAULT_DATA_NETWORK`] or another data network the instance is
+    /// attached to.
+    ///
+    /// The configurations of all networks of an instance are published to the
+    /// same topic of the instance's host and applied in order by the sidecar.
     pub network: String,
 
     /// IPv4 and IPv6 set the IP addresses of this network device. If
@@ -85,11 +119,14 @@ pub struct NetworkConfiguration {
     ///
     /// Your test-case will be assigned a B block in the range
     /// 16.0.0.1-32.0.0.0. X.Y.0.1 will always be reserved for the gateway
-    /// and shouldn't be used by the test.
+    /// and shouldn't be used by the test. See
+    /// [`RunParameters::data_network_block`](crate::RunParameters::data_network_block)
+    /// and [`RunParameters::data_network_gateway`](crate::RunParameters::data_network_gateway).
     #[serde(rename = "IPv4")]
     pub ipv4: Option<Ipv4Network>,
 
-    /// TODO: IPv6 is currently not supported.
+    /// See IPv4, e.g. for dual-stack tests. The address has to be within the
+    /// IPv6 data network, if any.
     #[serde(rename = "IPv6")]
     pub ipv6: Option<Ipv6Network>,
 
@@ -99,9 +136,8 @@ pub struct NetworkConfiguration {
     /// Default is the default link shaping rule.
     pub default: LinkShape,
 
-    /// Rules defines how traffic should be shaped to different subnets.
-    ///
-    /// TODO: This is not implemented.
+    /// Rules defines how traffic should be shaped to different subnets,
+    /// overriding the default link shape for traffic to the subnet of a rule.
     pub rules: Option<Vec<LinkRule>>,
 
     /// CallbackState will be signalled when the link changes are applied.
@@ -121,6 +157,186 @@ pub struct NetworkConfiguration {
     pub routing_policy: RoutingPolicyType,
 }
 
+impl NetworkConfiguration {
+    /// Allows access to external networks, e.g. the Internet, see
+    /// [`RoutingPolicyType::AllowAll`].
+    pub fn allow_external(mut self) -> Self {
+        self.routing_policy = RoutingPolicyType::AllowAll;
+        self
+    }
+
+    /// Denies access to external networks, e.g. the Internet, see
+    /// [`RoutingPolicyType::DenyAll`].
+    pub fn deny_external(mut self) -> Self {
+        self.routing_policy = RoutingPolicyType::DenyAll;
+        self
+    }
+}
+
+/// Builder for a [`LinkShape`], defaulting to an unshaped link, i.e. no
+/// latency, jitter, loss, corruption, reordering or duplication, and accepting
+/// all traffic.
+#[derive(Debug)]
+pub struct LinkShapeBuilder {
+    link_shape: LinkShape,
+}
+
+impl Default for LinkShapeBuilder {
+    fn default() -> Self {
+        Self {
+            link_shape: LinkShape {
+                latency: Duration::ZERO,
+                jitter: Duration::ZERO,
+                bandwidth: 0,
+                filter: FilterAction::Accept,
+                loss: 0.0,
+                corrupt: 0.0,
+                corrupt_corr: 0.0,
+                reorder: 0.0,
+                reorder_corr: 0.0,
+                duplicate: 0.0,
+                duplicate_corr: 0.0,
+            },
+        }
+    }
+}
+
+impl LinkShapeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.link_shape.latency = latency;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.link_shape.jitter = jitter;
+        self
+    }
+
+    /// Sets the egress bandwidth in bits per second.
+    pub fn bandwidth(mut self, bandwidth: u64) -> Self {
+        self.link_shape.bandwidth = bandwidth;
+        self
+    }
+
+    pub fn filter(mut self, filter: FilterAction) -> Self {
+        self.link_shape.filter = filter;
+        self
+    }
+
+    /// Sets the egress packet loss (%).
+    pub fn loss(mut self, loss: f32) -> Self {
+        self.link_shape.loss = loss;
+        self
+    }
+
+    /// Sets the egress packet corruption probability and correlation (%).
+    pub fn corrupt(mut self, corrupt: f32, corrupt_corr: f32) -> Self {
+        self.link_shape.corrupt = corrupt;
+        self.link_shape.corrupt_corr = corrupt_corr;
+        self
+    }
+
+    /// Sets the egress packet reordering probability and correlation (%).
+    pub fn reorder(mut self, reorder: f32, reorder_corr: f32) -> Self {
+        self.link_shape.reorder = reorder;
+        self.link_shape.reorder_corr = reorder_corr;
+        self
+    }
+
+    /// Sets the egress packet duplication percentage and correlation (%).
+    pub fn duplicate(mut self, duplicate: f32, duplicate_corr: f32) -> Self {
+        self.link_shape.duplicate = duplicate;
+        self.link_shape.duplicate_corr = duplicate_corr;
+        self
+    }
+
+    pub fn build(self) -> LinkShape {
+        self.link_shape
+    }
+}
+
+/// Builder for a [`NetworkConfiguration`] of the default data network,
+/// defaulting to an enabled, unshaped network allowing all routing, and
+/// leaving the IP addresses alone.
+#[derive(Debug)]
+pub struct NetworkConfigurationBuilder {
+    config: NetworkConfiguration,
+}
+
+impl NetworkConfigurationBuilder {
+    /// `callback_state` is signalled once the configuration is applied.
+    pub fn new(callback_state: impl Into<String>) -> Self {
+        Self {
+            config: NetworkConfiguration {
+                network: DEFAULT_DATA_NETWORK.to_owned(),
+                ipv4: None,
+                ipv6: None,
+                enable: true,
+                default: LinkShapeBuilder::new().build(),
+                rules: None,
+                callback_state: callback_state.into(),
+                callback_target: None,
+                routing_policy: RoutingPolicyType::AllowAll,
+            },
+        }
+    }
+
+    pub fn network(mut self, network: impl Into<String>) -> Self {
+        self.config.network = network.into();
+        self
+    }
+
+    pub fn ipv4(mut self, ipv4: Ipv4Network) -> Self {
+        self.config.ipv4 = Some(ipv4);
+        self
+    }
+
+    pub fn ipv6(mut self, ipv6: Ipv6Network) -> Self {
+        self.config.ipv6 = Some(ipv6);
+        self
+    }
+
+    pub fn enable(mut self, enable: bool) -> Self {
+        self.config.enable = enable;
+        self
+    }
+
+    /// Sets the default link shape, see [`LinkShapeBuilder`].
+    pub fn default_link_shape(mut self, link_shape: LinkShape) -> Self {
+        self.config.default = link_shape;
+        self
+    }
+
+    /// Adds a rule shaping the traffic to `subnet` with `link_shape`.
+    pub fn rule(mut self, subnet: IpNetwork, link_shape: LinkShape) -> Self {
+        self.config
+            .rules
+            .get_or_insert_with(Vec::new)
+            .push(LinkRule { link_shape, subnet });
+        self
+    }
+
+    /// Sets the amount of instances to wait for on the callback state,
+    /// defaulting to all instances of the run.
+    pub fn callback_target(mut self, callback_target: u64) -> Self {
+        self.config.callback_target = Some(callback_target);
+        self
+    }
+
+    pub fn routing_policy(mut self, routing_policy: RoutingPolicyType) -> Self {
+        self.config.routing_policy = routing_policy;
+        self
+    }
+
+    pub fn build(self) -> NetworkConfiguration {
+        self.config
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::net::Ipv4Addr;
@@ -137,8 +353,8 @@ mod tests {
             ipv6: None,
             enable: true,
             default: LinkShape {
-                latency: 10000000,
-                jitter: 0,
+                latency: Duration::from_millis(10),
+                jitter: Duration::ZERO,
                 bandwidth: 1048576,
                 filter: FilterAction::Accept,
                 loss: 0.0,
@@ -161,4 +377,137 @@ mod tests {
 
         assert_eq!(input, output)
     }
+
+    #[test]
+    fn serde_test_rules() {
+        let output = r#"{"network":"default","IPv4":null,"IPv6":null,"enable":true,"default":{"latency":10000000,"jitter":0,"bandwidth":1048576,"filter":0,"loss":0.0,"corrupt":0.0,"corrupt_corr":0.0,"reorder":0.0,"reorder_corr":0.0,"duplicate":0.0,"duplicate_corr":0.0},"rules":[{"latency":200000000,"jitter":0,"bandwidth":1048576,"filter":0,"loss":0.0,"corrupt":0.0,"corrupt_corr":0.0,"reorder":0.0,"reorder_corr":0.0,"duplicate":0.0,"duplicate_corr":0.0,"subnet":"16.0.1.0/24"},{"latency":10000000,"jitter":0,"bandwidth":1048576,"filter":2,"loss":0.0,"corrupt":0.0,"corrupt_corr":0.0,"reorder":0.0,"reorder_corr":0.0,"duplicate":0.0,"duplicate_corr":0.0,"subnet":"16.0.2.0/24"}],"callback_state":"rules-applied","routing_policy":"allow_all"}"#;
+
+        let link_shape = |latency, filter| LinkShape {
+            latency,
+            jitter: Duration::ZERO,
+            bandwidth: 1048576,
+            filter,
+            loss: 0.0,
+            corrupt: 0.0,
+            corrupt_corr: 0.0,
+            reorder: 0.0,
+            reorder_corr: 0.0,
+            duplicate: 0.0,
+            duplicate_corr: 0.0,
+        };
+
+        let network_conf = NetworkConfiguration {
+            network: DEFAULT_DATA_NETWORK.to_owned(),
+            ipv4: None,
+            ipv6: None,
+            enable: true,
+            default: link_shape(Duration::from_millis(10), FilterAction::Accept),
+            rules: Some(vec![
+                LinkRule {
+                    link_shape: link_shape(Duration::from_millis(200), FilterAction::Accept),
+                    subnet: "16.0.1.0/24".parse().unwrap(),
+                },
+                LinkRule {
+                    link_shape: link_shape(Duration::from_millis(10), FilterAction::Drop),
+                    subnet: "16.0.2.0/24".parse().unwrap(),
+                },
+            ]),
+            callback_state: "rules-applied".to_owned(),
+            callback_target: None,
+            routing_policy: RoutingPolicyType::AllowAll,
+        };
+
+        let input = serde_json::to_string(&network_conf).unwrap();
+
+        assert_eq!(input, output)
+    }
+
+    #[test]
+    fn serde_test_filter_action() {
+        for (action, encoded) in [
+            (FilterAction::Accept, "0"),
+            (FilterAction::Reject, "1"),
+            (FilterAction::Drop, "2"),
+        ] {
+            assert_eq!(serde_json::to_string(&action).unwrap(), encoded);
+            assert_eq!(
+                serde_json::from_str::<FilterAction>(encoded).unwrap(),
+                action
+            );
+        }
+
+        let link_shape = LinkShapeBuilder::new().filter(FilterAction::Drop).build();
+        let input = serde_json::to_value(&link_shape).unwrap();
+
+        assert_eq!(input["filter"], 2);
+        assert_eq!(
+            serde_json::from_value::<FilterAction>(input["filter"].clone()).unwrap(),
+            FilterAction::Drop
+        );
+    }
+
+    #[test]
+    fn serde_test_ipv6() {
+        let output = r#"{"network":"default","IPv4":"16.0.1.1/24","IPv6":"fd00:16::1:1/64","enable":true,"default":{"latency":0,"jitter":0,"bandwidth":0,"filter":0,"loss":0.0,"corrupt":0.0,"corrupt_corr":0.0,"reorder":0.0,"reorder_corr":0.0,"duplicate":0.0,"duplicate_corr":0.0},"rules":null,"callback_state":"dual-stack","routing_policy":"deny_all"}"#;
+
+        let network_conf = NetworkConfiguration {
+            network: DEFAULT_DATA_NETWORK.to_owned(),
+            ipv4: Some(Ipv4Network::new(Ipv4Addr::new(16, 0, 1, 1), 24).unwrap()),
+            ipv6: Some(Ipv6Network::new("fd00:16::1:1".parse().unwrap(), 64).unwrap()),
+            enable: true,
+            default: LinkShape {
+                latency: Duration::ZERO,
+                jitter: Duration::ZERO,
+                bandwidth: 0,
+                filter: FilterAction::Accept,
+                loss: 0.0,
+                corrupt: 0.0,
+                corrupt_corr: 0.0,
+                reorder: 0.0,
+                reorder_corr: 0.0,
+                duplicate: 0.0,
+                duplicate_corr: 0.0,
+            },
+            rules: None,
+            callback_state: "dual-stack".to_owned(),
+            callback_target: None,
+            routing_policy: RoutingPolicyType::DenyAll,
+        };
+
+        let input = serde_json::to_string(&network_conf).unwrap();
+
+        assert_eq!(input, output)
+    }
+
+    #[test]
+    fn external_routing() {
+        let network_conf = NetworkConfigurationBuilder::new("offline")
+            .build()
+            .deny_external();
+        let input = serde_json::to_value(&network_conf).unwrap();
+        assert_eq!(input["routing_policy"], "deny_all");
+
+        let input = serde_json::to_value(network_conf.allow_external()).unwrap();
+        assert_eq!(input["routing_policy"], "allow_all");
+    }
+
+    #[test]
+    fn builder() {
+        let output = r#"{"network":"default","IPv4":"16.0.1.1/24","IPv6":null,"enable":true,"default":{"latency":10000000,"jitter":0,"bandwidth":1048576,"filter":0,"loss":0.0,"corrupt":0.0,"corrupt_corr":0.0,"reorder":0.0,"reorder_corr":0.0,"duplicate":0.0,"duplicate_corr":0.0},"rules":null,"callback_state":"latency-reduced","routing_policy":"deny_all"}"#;
+
+        let network_conf = NetworkConfigurationBuilder::new("latency-reduced")
+            .ipv4(Ipv4Network::new(Ipv4Addr::new(16, 0, 1, 1), 24).unwrap())
+            .default_link_shape(
+                LinkShapeBuilder::new()
+                    .latency(Duration::from_millis(10))
+                    .bandwidth(1048576)
+                    .build(),
+            )
+            .routing_policy(RoutingPolicyType::DenyAll)
+            .build();
+
+        let input = serde_json::to_string(&network_conf).unwrap();
+
+        assert_eq!(input, output)
+    }
 }
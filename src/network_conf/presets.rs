@@ -0,0 +1,85 @@
+//! Presets of [`LinkShape`]s emulating common network conditions.
+//!
+//! The sidecar shapes the egress traffic of an instance, thus:
+//! - `latency` is the one-way delay, i.e. half the round trip time, such that
+//!   two instances applying the same preset observe the documented round trip
+//!   time.
+//! - `bandwidth` is the uplink, i.e. the rate at which an instance sends.
+
+use std::time::Duration;
+
+use super::{LinkShape, LinkShapeBuilder};
+
+impl LinkShape {
+    /// A 3G mobile link: 300 ms round trip time and a 768 Kbps uplink.
+    ///
+    /// Values of the `3G` connectivity profile of WebPageTest.
+    pub fn three_g() -> Self {
+        LinkShapeBuilder::new()
+            .latency(Duration::from_millis(150))
+            .bandwidth(768_000)
+            .build()
+    }
+
+    /// A 4G LTE mobile link: 70 ms round trip time and a 12 Mbps uplink.
+    ///
+    /// Values of the `LTE` connectivity profile of WebPageTest.
+    pub fn lte() -> Self {
+        LinkShapeBuilder::new()
+            .latency(Duration::from_millis(35))
+            .bandwidth(12_000_000)
+            .build()
+    }
+
+    /// A geostationary satellite link: 600 ms round trip time, 20 ms jitter,
+    /// a 3 Mbps uplink and 0.5% loss.
+    ///
+    /// The signal travels about 36,000 km up to the satellite and back down,
+    /// taking at least 240 ms one way, to which terrestrial routing and
+    /// processing add. Bandwidth, jitter and loss are typical of consumer
+    /// plans, which vary widely.
+    pub fn satellite() -> Self {
+        LinkShapeBuilder::new()
+            .latency(Duration::from_millis(300))
+            .jitter(Duration::from_millis(20))
+            .bandwidth(3_000_000)
+            .loss(0.5)
+            .build()
+    }
+
+    /// A link within a datacenter or a wired LAN: 500 µs round trip time and
+    /// a 10 Gbps uplink.
+    ///
+    /// The round trip time within a datacenter of the "Latency Numbers Every
+    /// Programmer Should Know".
+    pub fn datacenter() -> Self {
+        LinkShapeBuilder::new()
+            .latency(Duration::from_micros(250))
+            .bandwidth(10_000_000_000)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_times() {
+        let presets = [
+            (LinkShape::three_g(), 300),
+            (LinkShape::lte(), 70),
+            (LinkShape::satellite(), 600),
+        ];
+
+        for (link_shape, round_trip_ms) in presets {
+            assert_eq!(link_shape.latency * 2, Duration::from_millis(round_trip_ms));
+            assert!(link_shape.bandwidth > 0);
+        }
+
+        assert_eq!(
+            LinkShape::datacenter().latency * 2,
+            Duration::from_micros(500)
+        );
+    }
+}
@@ -1,89 +1,572 @@
 use clap::Parser;
 use std::collections::HashMap;
-use std::net::{IpAddr, Ipv4Addr};
+use std::ffi::OsString;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
 
 use chrono::{DateTime, FixedOffset};
 use std::path::PathBuf;
 
-use ipnetwork::IpNetwork;
+use ipnetwork::{IpNetwork, Ipv4Network};
+use serde::{Deserialize, Serialize};
 
-#[derive(Parser, Debug, Clone)]
+use crate::errors::ParamsError;
+
+#[derive(Parser, Serialize, Deserialize, Debug, Clone)]
 /// RunParameters encapsulates the runtime parameters for this test.
+///
+/// Serialized like the run environment of the Go SDK, e.g. `"plan"`,
+/// `"instances"` and `"network"`, for the parameters it knows about, as
+/// carried by the `start_event`.
 pub struct RunParameters {
     #[clap(env)]
+    #[serde(rename = "plan")]
     pub test_plan: String, // TEST_PLAN: streaming_test
     #[clap(env)]
+    #[serde(rename = "case")]
     pub test_case: String, // TEST_CASE: quickstart
     #[clap(env)]
+    #[serde(rename = "run")]
     pub test_run: String, // TEST_RUN: c7fjstge5te621cen4i0
 
     #[clap(env)]
+    #[serde(rename = "repo", default, skip_serializing_if = "String::is_empty")]
     pub test_repo: String, //TEST_REPO:
     #[clap(env)]
+    #[serde(rename = "branch", default, skip_serializing_if = "String::is_empty")]
     pub test_branch: String, // TEST_BRANCH:
     #[clap(env)]
+    #[serde(rename = "tag", default, skip_serializing_if = "String::is_empty")]
     pub test_tag: String, // TEST_TAG:
 
     #[clap(env)]
+    #[serde(rename = "outputs_path", default)]
     pub test_outputs_path: PathBuf, // TEST_OUTPUTS_PATH: /outputs
     #[clap(env)]
+    #[serde(rename = "temp_path", default)]
     pub test_temp_path: String, // TEST_TEMP_PATH: /temp
 
     #[clap(env)]
+    #[serde(rename = "instances")]
     pub test_instance_count: u64, // TEST_INSTANCE_COUNT: 1
     #[clap(env)]
+    #[serde(rename = "role", default, skip_serializing_if = "String::is_empty")]
     pub test_instance_role: String, // TEST_INSTANCE_ROLE:
     #[clap(env, parse(try_from_str = parse_key_val))]
+    #[serde(rename = "params", default, skip_serializing_if = "HashMap::is_empty")]
     pub test_instance_params: HashMap<String, String>, // TEST_INSTANCE_PARAMS: feature=false|neutral_nodes=10|num=2|word=never
 
     #[clap(long, env)]
+    #[serde(default)]
     pub test_sidecar: bool, // TEST_SIDECAR: true
 
-    #[clap(env)]
+    #[clap(env, parse(try_from_str = parse_subnet))]
+    #[serde(rename = "network")]
     pub test_subnet: IpNetwork, // TEST_SUBNET: 16.0.0.0/16
     #[clap(env)]
+    #[serde(rename = "start_time")]
     pub test_start_time: DateTime<FixedOffset>, // TEST_START_TIME: 2022-01-12T15:48:07-05:00
 
+    #[clap(env, parse(try_from_str = parse_duration))]
+    #[serde(
+        rename = "run_timeout",
+        default,
+        with = "optional_nanos",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub test_run_timeout: Option<Duration>, // TEST_RUN_TIMEOUT: 10m
+
     #[clap(env)]
+    #[serde(
+        rename = "capture_profiles",
+        default,
+        with = "profiles_map",
+        skip_serializing_if = "String::is_empty"
+    )]
     pub test_capture_profiles: String, // TEST_CAPTURE_PROFILES:
 
     #[clap(env)]
+    #[serde(rename = "group_instances", default)]
     pub test_group_instance_count: u64, // TEST_GROUP_INSTANCE_COUNT: 1
     #[clap(env)]
+    #[serde(rename = "group", default)]
     pub test_group_id: String, // TEST_GROUP_ID: single
 
     #[clap(long, env)]
+    #[serde(rename = "disable_metrics", default)]
     pub test_disable_metrics: bool, // TEST_DISABLE_METRICS: false
 
+    /// Runs against an in-process sync service instead of connecting to the
+    /// one of the Testground daemon, e.g. to iterate on a single instance
+    /// locally.
+    #[clap(long, env)]
+    #[serde(default)]
+    pub testground_no_sync: bool, // TESTGROUND_NO_SYNC: false
+
     #[clap(env)]
+    #[serde(default)]
     pub hostname: String, // HOSTNAME: e6f4cc8fc147
     #[clap(env)]
+    #[serde(default)]
     pub influxdb_url: String, // INFLUXDB_URL: http://testground-influxdb:8086
     #[clap(env)]
+    #[serde(default)]
     pub redis_host: String, // REDIS_HOST: testground-redis
-                            // HOME: /
+    #[clap(env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_service_host: Option<String>, // SYNC_SERVICE_HOST: testground-sync-service
+    #[clap(env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_service_port: Option<u16>, // SYNC_SERVICE_PORT: 5050
+    #[clap(env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kubernetes_service_host: Option<String>, // KUBERNETES_SERVICE_HOST: 10.96.0.1
+                                                 // HOME: /
+}
+
+/// The Testground runner executing this test instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunnerKind {
+    /// `local:exec`, running instances as plain processes, without a sidecar.
+    LocalExec,
+    /// `local:docker`, running instances as containers next to a sidecar.
+    Docker,
+    /// `cluster:k8s`, running instances as pods on a Kubernetes cluster.
+    Cluster,
+}
+
+impl std::fmt::Display for RunnerKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunnerKind::LocalExec => write!(f, "local:exec"),
+            RunnerKind::Docker => write!(f, "local:docker"),
+            RunnerKind::Cluster => write!(f, "cluster:k8s"),
+        }
+    }
+}
+
+/// A profile to capture, as requested by `test_capture_profiles`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileSpec {
+    pub kind: ProfileKind,
+    /// How long to capture the profile for, or `None` for a single snapshot.
+    pub duration: Option<Duration>,
+}
+
+/// The kind of a [`ProfileSpec`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProfileKind {
+    Cpu,
+    Mem,
+    Block,
+    Goroutine,
+    Custom(String),
+}
+
+impl From<&str> for ProfileKind {
+    fn from(kind: &str) -> Self {
+        match kind {
+            "cpu" => ProfileKind::Cpu,
+            "mem" => ProfileKind::Mem,
+            "block" => ProfileKind::Block,
+            "goroutine" => ProfileKind::Goroutine,
+            kind => ProfileKind::Custom(kind.to_owned()),
+        }
+    }
 }
 
 impl RunParameters {
+    /// Reads the runtime parameters from the environment, like
+    /// `RunParameters::try_parse`, but failing with a [`ParamsError`] naming
+    /// the variable missing or malformed, e.g. `TEST_INSTANCE_COUNT not a
+    /// u64`, rather than with a usage error.
+    ///
+    /// Pass the parameters to
+    /// [`ClientBuilder::run_parameters`](crate::client::ClientBuilder::run_parameters)
+    /// to build a client from them.
+    pub fn from_env() -> Result<Self, ParamsError> {
+        Self::from_vars(|name| std::env::var_os(name))
+    }
+
+    fn from_vars(lookup: impl Fn(&str) -> Option<OsString>) -> Result<Self, ParamsError> {
+        let vars = Vars(lookup);
+
+        Ok(Self {
+            test_plan: vars.required("TEST_PLAN")?,
+            test_case: vars.required("TEST_CASE")?,
+            test_run: vars.required("TEST_RUN")?,
+            test_repo: vars.required("TEST_REPO")?,
+            test_branch: vars.required("TEST_BRANCH")?,
+            test_tag: vars.required("TEST_TAG")?,
+            test_outputs_path: vars.required("TEST_OUTPUTS_PATH")?.into(),
+            test_temp_path: vars.required("TEST_TEMP_PATH")?,
+            test_instance_count: vars.parse("TEST_INSTANCE_COUNT", "u64", str::parse)?,
+            test_instance_role: vars.required("TEST_INSTANCE_ROLE")?,
+            test_instance_params: vars.parse(
+                "TEST_INSTANCE_PARAMS",
+                "list of KEY=VALUE",
+                parse_key_val,
+            )?,
+            test_sidecar: vars.flag("TEST_SIDECAR")?,
+            test_subnet: {
+                let value = vars.required("TEST_SUBNET")?;
+                parse_subnet(&value)
+                    .map_err(|reason| ParamsError::InvalidSubnet { value, reason })?
+            },
+            test_start_time: vars.parse("TEST_START_TIME", "RFC 3339 date", str::parse)?,
+            test_run_timeout: vars.optional("TEST_RUN_TIMEOUT", "duration", parse_duration)?,
+            test_capture_profiles: vars.required("TEST_CAPTURE_PROFILES")?,
+            test_group_instance_count: vars.parse(
+                "TEST_GROUP_INSTANCE_COUNT",
+                "u64",
+                str::parse,
+            )?,
+            test_group_id: vars.required("TEST_GROUP_ID")?,
+            test_disable_metrics: vars.flag("TEST_DISABLE_METRICS")?,
+            testground_no_sync: vars.flag("TESTGROUND_NO_SYNC")?,
+            hostname: vars.required("HOSTNAME")?,
+            influxdb_url: vars.required("INFLUXDB_URL")?,
+            redis_host: vars.required("REDIS_HOST")?,
+            sync_service_host: vars
+                .optional("SYNC_SERVICE_HOST", "string", |s| Ok::<_, ()>(s.to_owned()))?,
+            sync_service_port: vars.optional("SYNC_SERVICE_PORT", "u16", str::parse)?,
+            kubernetes_service_host: vars.optional("KUBERNETES_SERVICE_HOST", "string", |s| {
+                Ok::<_, ()>(s.to_owned())
+            })?,
+        })
+    }
+
     /// Examines the local network interfaces, and tries to find our assigned IP
     /// within the data network.
     ///
+    /// The address is of the same family as `test_subnet`, thus an IPv6
+    /// address is returned for an IPv6 data network.
+    ///
     /// If running in a sidecar-less environment, the loopback address is
     /// returned.
     pub fn data_network_ip(&self) -> std::io::Result<Option<IpAddr>> {
         if !self.test_sidecar {
             // This must be a local:exec runner and we currently don't support
             // traffic shaping on it for now, just return the loopback address.
-            return Ok(Some(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))));
+            return Ok(Some(match self.test_subnet {
+                IpNetwork::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpNetwork::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            }));
+        }
+
+        Ok(self.find_data_network_ip(if_addrs::get_if_addrs()?.into_iter().map(|i| i.addr.ip())))
+    }
+
+    /// Returns the first of `ips` within `test_subnet`, skipping addresses
+    /// of the other family.
+    fn find_data_network_ip(&self, ips: impl IntoIterator<Item = IpAddr>) -> Option<IpAddr> {
+        ips.into_iter().find(|ip| self.test_subnet.contains(*ip))
+    }
+
+    /// Returns the IPv4 data network block of the test case, i.e. the network
+    /// of `test_subnet`, typically a `/16` such as `16.0.0.0/16`, or `None`
+    /// for an IPv6 data network.
+    pub fn data_network_block(&self) -> Option<Ipv4Network> {
+        match self.test_subnet {
+            IpNetwork::V4(subnet) => Ipv4Network::new(subnet.network(), subnet.prefix()).ok(),
+            IpNetwork::V6(_) => None,
+        }
+    }
+
+    /// Returns the gateway of the IPv4 data network, i.e. the first address of
+    /// [`RunParameters::data_network_block`], e.g. `X.Y.0.1` for a `/16`, or
+    /// `None` for an IPv6 data network.
+    pub fn data_network_gateway(&self) -> Option<Ipv4Addr> {
+        let block = self.data_network_block()?;
+
+        Some(Ipv4Addr::from(u32::from(block.network()) + 1))
+    }
+
+    /// Infers the runner executing this test instance.
+    ///
+    /// Only `local:exec` runs without a sidecar, while `cluster:k8s` is told
+    /// apart from `local:docker` by the Kubernetes environment.
+    pub fn runner_kind(&self) -> RunnerKind {
+        if !self.test_sidecar {
+            RunnerKind::LocalExec
+        } else if self.kubernetes_service_host.is_some() {
+            RunnerKind::Cluster
+        } else {
+            RunnerKind::Docker
         }
+    }
+
+    /// Returns the number of instances of the run, across all groups, i.e.
+    /// `test_instance_count`. The target of barriers awaiting all instances.
+    pub fn global_instance_count(&self) -> u64 {
+        self.test_instance_count
+    }
+
+    /// Returns the number of instances in the group of this instance, i.e.
+    /// `test_group_instance_count`. The target of group-scoped barriers, see
+    /// [`Client::barrier_group`](crate::client::Client::barrier_group).
+    pub fn group_instance_count(&self) -> u64 {
+        self.test_group_instance_count
+    }
+
+    /// Returns the address of the sync service, i.e. `sync_service_host` and
+    /// `sync_service_port`, defaulting to `testground-sync-service:5050`.
+    pub fn sync_service_addr(&self) -> (String, u16) {
+        (
+            self.sync_service_host
+                .clone()
+                .unwrap_or_else(|| "testground-sync-service".to_owned()),
+            self.sync_service_port.unwrap_or(5050),
+        )
+    }
+
+    /// Returns the point in time after which the run is expected to be killed,
+    /// i.e. `test_start_time` plus `test_run_timeout`, or `None` if no timeout
+    /// is set.
+    pub fn run_deadline(&self) -> Option<DateTime<FixedOffset>> {
+        let timeout = chrono::Duration::from_std(self.test_run_timeout?).ok()?;
+
+        self.test_start_time.checked_add_signed(timeout)
+    }
+
+    /// Parses `test_capture_profiles`, a comma separated list of profile
+    /// kinds, each optionally followed by a duration, e.g. `cpu=30s,mem`.
+    ///
+    /// Returns no profiles if `test_capture_profiles` is empty.
+    pub fn capture_profiles(&self) -> Result<Vec<ProfileSpec>, String> {
+        parse_profiles(&self.test_capture_profiles)
+    }
+
+    /// Returns the path of `name` within `test_outputs_path`, where the
+    /// daemon collects the outputs of the instance, e.g. pcaps, logs or
+    /// results, creating the directory if missing.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if `name` isn't a
+    /// plain file name, e.g. contains a path separator, thus escaping the
+    /// outputs directory, and with [`std::io::ErrorKind::NotFound`] if no
+    /// outputs path is set.
+    pub fn output_path(&self, name: &str) -> std::io::Result<PathBuf> {
+        let is_file_name =
+            !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\']);
+        if !is_file_name {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Invalid output file name: {:?}", name),
+            ));
+        }
+
+        let path = self.output_path_if_set(name).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "No outputs path set")
+        })?;
+        std::fs::create_dir_all(&self.test_outputs_path)?;
+
+        Ok(path)
+    }
+
+    /// Creates (or truncates) the file `name` within `test_outputs_path`, see
+    /// [`RunParameters::output_path`].
+    pub fn output_file(&self, name: &str) -> std::io::Result<std::fs::File> {
+        std::fs::File::create(self.output_path(name)?)
+    }
+
+    /// Returns the path of `name` within `test_outputs_path`, or `None` if no
+    /// outputs path is set.
+    pub(crate) fn output_path_if_set(&self, name: &str) -> Option<PathBuf> {
+        if self.test_outputs_path.as_os_str().is_empty() {
+            return None;
+        }
+
+        Some(self.test_outputs_path.join(name))
+    }
+
+    /// Derives a seed from `test_run`, `test_group_id` and the given `salt`,
+    /// for plans needing reproducible randomness.
+    ///
+    /// The seed is stable across a run, i.e. re-running the same run ID
+    /// yields the same seed, but differs between runs, groups and salts. All
+    /// instances of a group share the same seed, see
+    /// [`Client::deterministic_seed`](crate::client::Client::deterministic_seed)
+    /// for one distinct per instance.
+    pub fn deterministic_seed(&self, salt: &str) -> u64 {
+        self.seed(salt, None)
+    }
+
+    pub(crate) fn seed(&self, salt: &str, seq: Option<u64>) -> u64 {
+        // FNV-1a, as opposed to `std`'s `DefaultHasher`, is guaranteed to be
+        // stable across Rust releases and platforms.
+        const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const PRIME: u64 = 0x100000001b3;
+
+        let mut hash = OFFSET_BASIS;
+
+        let fields = [
+            self.test_run.as_bytes(),
+            self.test_group_id.as_bytes(),
+            salt.as_bytes(),
+        ];
+        let seq = seq.map(u64::to_be_bytes);
+
+        for field in fields.into_iter().chain(seq.as_ref().map(|s| &s[..])) {
+            // Separate fields so that e.g. ("ab", "c") and ("a", "bc") differ.
+            for byte in field.iter().chain(std::iter::once(&0xff)) {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(PRIME);
+            }
+        }
+
+        hash
+    }
+}
+
+/// Environment variables read by [`RunParameters::from_env`].
+struct Vars<F>(F);
+
+impl<F: Fn(&str) -> Option<OsString>> Vars<F> {
+    /// The value of `name`, or `None` if unset.
+    fn get(&self, name: &'static str) -> Result<Option<String>, ParamsError> {
+        (self.0)(name)
+            .map(|value| {
+                value.into_string().map_err(|value| ParamsError::Malformed {
+                    name,
+                    expected: "UTF-8 string",
+                    value: value.to_string_lossy().into_owned(),
+                })
+            })
+            .transpose()
+    }
+
+    /// The value of `name`, possibly empty.
+    fn required(&self, name: &'static str) -> Result<String, ParamsError> {
+        self.get(name)?.ok_or(ParamsError::Missing(name))
+    }
+
+    fn parse<T, E>(
+        &self,
+        name: &'static str,
+        expected: &'static str,
+        parse: impl Fn(&str) -> Result<T, E>,
+    ) -> Result<T, ParamsError> {
+        let value = self.required(name)?;
+
+        parse(&value).map_err(|_| ParamsError::Malformed {
+            name,
+            expected,
+            value,
+        })
+    }
+
+    /// Like [`Vars::parse`], but `None` if unset or empty.
+    fn optional<T, E>(
+        &self,
+        name: &'static str,
+        expected: &'static str,
+        parse: impl Fn(&str) -> Result<T, E>,
+    ) -> Result<Option<T>, ParamsError> {
+        match self.get(name)? {
+            Some(value) if !value.is_empty() => self.parse(name, expected, parse).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// A flag, `false` if unset or empty, accepting the same values as clap.
+    fn flag(&self, name: &'static str) -> Result<bool, ParamsError> {
+        self.optional(name, "bool", |value| {
+            match value.to_ascii_lowercase().as_str() {
+                "y" | "yes" | "t" | "true" | "on" | "1" => Ok(true),
+                "n" | "no" | "f" | "false" | "off" | "0" => Ok(false),
+                _ => Err(()),
+            }
+        })
+        .map(Option::unwrap_or_default)
+    }
+}
+
+/// (De)serializes `test_run_timeout` as nanoseconds, like Go's
+/// `time.Duration`.
+mod optional_nanos {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => {
+                serializer.serialize_u64(duration.as_nanos().try_into().unwrap_or(u64::MAX))
+            }
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_nanos))
+    }
+}
+
+/// (De)serializes `test_capture_profiles`, e.g. `cpu=30s,mem`, as a map of
+/// profile kinds to durations, e.g. `{"cpu":"30s","mem":""}`, like the Go
+/// SDK.
+mod profiles_map {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(profiles: &str, serializer: S) -> Result<S::Ok, S::Error> {
+        profiles
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(|p| match p.split_once('=') {
+                Some((kind, duration)) => (kind.trim(), duration.trim()),
+                None => (p, ""),
+            })
+            .collect::<BTreeMap<_, _>>()
+            .serialize(serializer)
+    }
 
-        Ok(if_addrs::get_if_addrs()?
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        let profiles = BTreeMap::<String, String>::deserialize(deserializer)?
             .into_iter()
-            .map(|i| i.addr.ip())
-            .find(|ip| self.test_subnet.contains(*ip)))
+            .map(|(kind, duration)| match duration.is_empty() {
+                true => kind,
+                false => format!("{}={}", kind, duration),
+            })
+            .collect::<Vec<_>>();
+
+        Ok(profiles.join(","))
     }
 }
 
+/// Parses `TEST_SUBNET`, either an IPv4 or an IPv6 network, rejecting
+/// networks that can't hold the addresses of multiple instances.
+fn parse_subnet(s: &str) -> Result<IpNetwork, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err("empty subnet".to_owned());
+    }
+
+    let subnet: IpNetwork = s
+        .parse()
+        .map_err(|e: ipnetwork::IpNetworkError| e.to_string())?;
+
+    let max_prefix = match subnet {
+        IpNetwork::V4(_) => 32,
+        IpNetwork::V6(_) => 128,
+    };
+    if subnet.prefix() == 0 {
+        return Err("subnet spans all addresses".to_owned());
+    }
+    if subnet.prefix() == max_prefix {
+        return Err("subnet holds a single address".to_owned());
+    }
+
+    Ok(subnet)
+}
+
 fn parse_key_val(s: &str) -> Result<HashMap<String, String>, String> {
     let mut hashmap = HashMap::new();
 
@@ -97,6 +580,325 @@ fn parse_key_val(s: &str) -> Result<HashMap<String, String>, String> {
     Ok(hashmap)
 }
 
+fn parse_profiles(s: &str) -> Result<Vec<ProfileSpec>, String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (kind, duration) = match p.split_once('=') {
+                Some((kind, duration)) => (kind.trim(), Some(duration.trim())),
+                None => (p, None),
+            };
+
+            if kind.is_empty() {
+                return Err(format!("Invalid profile: no kind found in {}", p));
+            }
+
+            let duration = match duration {
+                Some("") => return Err(format!("Invalid profile: no duration found in {}", p)),
+                Some(duration) => Some(parse_duration(duration)?),
+                None => None,
+            };
+
+            Ok(ProfileSpec {
+                kind: kind.into(),
+                duration,
+            })
+        })
+        .collect()
+}
+
+/// Parses a timeout either in seconds (`600`) or in the duration format of
+/// Go's `time.Duration` (`10m`, `1h30m`, `1.5s`, `300ms`).
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    if let Ok(secs) = s.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let invalid = || format!("Invalid duration: {}", s);
+
+    let mut rest = s;
+    let mut total = 0f64;
+
+    while !rest.is_empty() {
+        let value_len = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .ok_or_else(invalid)?;
+        let value: f64 = rest[..value_len].parse().map_err(|_| invalid())?;
+        rest = &rest[value_len..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let unit = match &rest[..unit_len] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 1e-3,
+            "us" | "µs" => 1e-6,
+            "ns" => 1e-9,
+            _ => return Err(invalid()),
+        };
+        rest = &rest[unit_len..];
+
+        total += value * unit;
+    }
+
+    Duration::try_from_secs_f64(total).map_err(|_| invalid())
+}
+
+#[cfg(any(test, feature = "testing"))]
+pub(crate) fn test_run_parameters() -> RunParameters {
+    RunParameters {
+        test_plan: "sdk-rust".to_owned(),
+        test_case: "example".to_owned(),
+        test_run: "c7fjstge5te621cen4i0".to_owned(),
+        test_repo: String::new(),
+        test_branch: String::new(),
+        test_tag: String::new(),
+        test_outputs_path: PathBuf::new(),
+        test_temp_path: "/temp".to_owned(),
+        test_instance_count: 1,
+        test_instance_role: String::new(),
+        test_instance_params: HashMap::new(),
+        test_sidecar: false,
+        test_subnet: "16.0.0.0/16".parse().unwrap(),
+        test_start_time: DateTime::parse_from_rfc3339("2022-01-12T15:48:07-05:00").unwrap(),
+        test_run_timeout: None,
+        test_capture_profiles: String::new(),
+        test_group_instance_count: 1,
+        test_group_id: "single".to_owned(),
+        test_disable_metrics: false,
+        testground_no_sync: false,
+        hostname: "e6f4cc8fc147".to_owned(),
+        influxdb_url: "http://testground-influxdb:8086".to_owned(),
+        redis_host: "testground-redis".to_owned(),
+        sync_service_host: None,
+        sync_service_port: None,
+        kubernetes_service_host: None,
+    }
+}
+
+#[test]
+fn test_deterministic_seed() {
+    let params = test_run_parameters();
+
+    assert_eq!(
+        params.deterministic_seed("peers"),
+        params.deterministic_seed("peers")
+    );
+    assert_ne!(
+        params.deterministic_seed("peers"),
+        params.deterministic_seed("topics")
+    );
+    assert_ne!(params.seed("peers", Some(1)), params.seed("peers", Some(2)));
+
+    let mut other_group = test_run_parameters();
+    other_group.test_group_id = "other".to_owned();
+    assert_ne!(
+        params.deterministic_seed("peers"),
+        other_group.deterministic_seed("peers")
+    );
+
+    let mut other_run = test_run_parameters();
+    other_run.test_run = "c7fjstge5te621cen4j0".to_owned();
+    assert_ne!(
+        params.deterministic_seed("peers"),
+        other_run.deterministic_seed("peers")
+    );
+}
+
+#[test]
+fn test_runner_kind() {
+    let mut params = test_run_parameters();
+    assert_eq!(params.runner_kind(), RunnerKind::LocalExec);
+
+    params.test_sidecar = true;
+    assert_eq!(params.runner_kind(), RunnerKind::Docker);
+
+    params.kubernetes_service_host = Some("10.96.0.1".to_owned());
+    assert_eq!(params.runner_kind(), RunnerKind::Cluster);
+}
+
+#[test]
+fn test_instance_counts() {
+    let mut params = test_run_parameters();
+    params.test_instance_count = 3;
+    params.test_group_instance_count = 2;
+
+    assert_eq!(params.global_instance_count(), 3);
+    assert_eq!(params.group_instance_count(), 2);
+}
+
+#[test]
+fn test_sync_service_addr() {
+    let mut params = test_run_parameters();
+    assert_eq!(
+        params.sync_service_addr(),
+        ("testground-sync-service".to_owned(), 5050)
+    );
+
+    params.sync_service_host = Some("127.0.0.1".to_owned());
+    params.sync_service_port = Some(5051);
+    assert_eq!(params.sync_service_addr(), ("127.0.0.1".to_owned(), 5051));
+}
+
+#[test]
+fn test_data_network_ip_without_sidecar() {
+    let mut params = test_run_parameters();
+    assert_eq!(
+        params.data_network_ip().unwrap(),
+        Some(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    );
+
+    params.test_subnet = "fd00:16::/64".parse().unwrap();
+    assert_eq!(
+        params.data_network_ip().unwrap(),
+        Some(IpAddr::V6(Ipv6Addr::LOCALHOST))
+    );
+}
+
+#[test]
+fn test_data_network_ip_v6() {
+    let mut params = test_run_parameters();
+    params.test_sidecar = true;
+    params.test_subnet = parse_subnet("fd00:16::/64").unwrap();
+
+    let ips = [
+        IpAddr::V6(Ipv6Addr::LOCALHOST),
+        "16.0.0.1".parse().unwrap(),
+        "fd00:16::3".parse().unwrap(),
+    ];
+    assert_eq!(
+        params.find_data_network_ip(ips),
+        Some("fd00:16::3".parse().unwrap())
+    );
+}
+
+#[test]
+fn test_parse_subnet() {
+    assert!(matches!(parse_subnet("16.0.0.0/16"), Ok(IpNetwork::V4(_))));
+    assert!(matches!(
+        parse_subnet(" fd00:16::/64 "),
+        Ok(IpNetwork::V6(_))
+    ));
+
+    assert!(parse_subnet("").is_err());
+    assert!(parse_subnet("16.0.0/16").is_err());
+    assert!(parse_subnet("16.0.0.0/33").is_err());
+    assert!(parse_subnet("0.0.0.0/0").is_err());
+    assert!(parse_subnet("16.0.0.1/32").is_err());
+    assert!(parse_subnet("fd00:16::1/128").is_err());
+}
+
+#[test]
+fn test_data_network_gateway() {
+    let mut params = test_run_parameters();
+    assert_eq!(
+        params.data_network_block(),
+        Some("16.0.0.0/16".parse().unwrap())
+    );
+    assert_eq!(
+        params.data_network_gateway(),
+        Some(Ipv4Addr::new(16, 0, 0, 1))
+    );
+
+    // The subnet might be given by any of its addresses.
+    params.test_subnet = "16.3.7.9/24".parse().unwrap();
+    assert_eq!(
+        params.data_network_block(),
+        Some("16.3.7.0/24".parse().unwrap())
+    );
+    assert_eq!(
+        params.data_network_gateway(),
+        Some(Ipv4Addr::new(16, 3, 7, 1))
+    );
+
+    params.test_subnet = "fd00:16::/64".parse().unwrap();
+    assert_eq!(params.data_network_block(), None);
+    assert_eq!(params.data_network_gateway(), None);
+}
+
+#[test]
+fn test_run_deadline() {
+    let mut params = test_run_parameters();
+    assert_eq!(params.run_deadline(), None);
+
+    params.test_run_timeout = Some(parse_duration("1h30m").unwrap());
+    assert_eq!(
+        params.run_deadline(),
+        Some(DateTime::parse_from_rfc3339("2022-01-12T17:18:07-05:00").unwrap())
+    );
+}
+
+#[test]
+fn test_start_time_negative_offset() {
+    use chrono::{TimeZone, Utc};
+
+    // `TEST_START_TIME` is parsed by clap through `DateTime`'s `FromStr`.
+    let start_time: DateTime<FixedOffset> = "2022-01-12T15:48:07-05:00".parse().unwrap();
+
+    assert_eq!(
+        start_time.offset(),
+        &FixedOffset::west_opt(5 * 3600).unwrap()
+    );
+    assert_eq!(
+        start_time,
+        Utc.with_ymd_and_hms(2022, 1, 12, 20, 48, 7).unwrap()
+    );
+    assert_eq!(start_time, test_run_parameters().test_start_time);
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("600").unwrap(), Duration::from_secs(600));
+    assert_eq!(parse_duration("10m").unwrap(), Duration::from_secs(600));
+    assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+    assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+    assert_eq!(parse_duration("300ms").unwrap(), Duration::from_millis(300));
+
+    assert!(parse_duration("").is_ok());
+    assert!(parse_duration("10").is_ok());
+    assert!(parse_duration("m").is_err());
+    assert!(parse_duration("10x").is_err());
+    assert!(parse_duration("10m5").is_err());
+}
+
+#[test]
+fn test_parse_profiles() {
+    assert_eq!(parse_profiles("").unwrap(), vec![]);
+
+    assert_eq!(
+        parse_profiles("cpu=30s,mem, block=1m,goroutine,allocs=5s").unwrap(),
+        vec![
+            ProfileSpec {
+                kind: ProfileKind::Cpu,
+                duration: Some(Duration::from_secs(30)),
+            },
+            ProfileSpec {
+                kind: ProfileKind::Mem,
+                duration: None,
+            },
+            ProfileSpec {
+                kind: ProfileKind::Block,
+                duration: Some(Duration::from_secs(60)),
+            },
+            ProfileSpec {
+                kind: ProfileKind::Goroutine,
+                duration: None,
+            },
+            ProfileSpec {
+                kind: ProfileKind::Custom("allocs".to_owned()),
+                duration: Some(Duration::from_secs(5)),
+            },
+        ]
+    );
+
+    assert!(parse_profiles("cpu=").is_err());
+    assert!(parse_profiles("=30s").is_err());
+    assert!(parse_profiles("cpu=30x").is_err());
+}
+
 #[test]
 fn test_parse_key_val() {
     let result = parse_key_val("feature=false|neutral_nodes=10|num=2|word=never").unwrap();
@@ -120,3 +922,141 @@ fn test_parse_key_val() {
     let result = parse_key_val("feature=false|neutral_nodes");
     assert!(result.is_err());
 }
+
+#[test]
+fn test_from_env() {
+    let mut vars: HashMap<&str, &str> = [
+        ("TEST_PLAN", "sdk-rust"),
+        ("TEST_CASE", "example"),
+        ("TEST_RUN", "c7fjstge5te621cen4i0"),
+        ("TEST_REPO", ""),
+        ("TEST_BRANCH", ""),
+        ("TEST_TAG", ""),
+        ("TEST_OUTPUTS_PATH", "/outputs"),
+        ("TEST_TEMP_PATH", "/temp"),
+        ("TEST_INSTANCE_COUNT", "2"),
+        ("TEST_INSTANCE_ROLE", ""),
+        ("TEST_INSTANCE_PARAMS", "num=2|word=never"),
+        ("TEST_SIDECAR", "true"),
+        ("TEST_SUBNET", "16.0.0.0/16"),
+        ("TEST_START_TIME", "2022-01-12T15:48:07-05:00"),
+        ("TEST_CAPTURE_PROFILES", ""),
+        ("TEST_GROUP_INSTANCE_COUNT", "1"),
+        ("TEST_GROUP_ID", "single"),
+        ("HOSTNAME", "e6f4cc8fc147"),
+        ("INFLUXDB_URL", "http://testground-influxdb:8086"),
+        ("REDIS_HOST", "testground-redis"),
+        ("SYNC_SERVICE_PORT", "5051"),
+    ]
+    .into_iter()
+    .collect();
+
+    let from_vars = |vars: &HashMap<&str, &str>| {
+        RunParameters::from_vars(|name| vars.get(name).map(OsString::from))
+    };
+
+    let params = from_vars(&vars).unwrap();
+    assert_eq!(params.test_instance_count, 2);
+    assert_eq!(params.test_instance_params.len(), 2);
+    assert!(params.test_sidecar);
+    assert!(!params.test_disable_metrics);
+    assert_eq!(params.test_run_timeout, None);
+    assert_eq!(
+        params.sync_service_addr(),
+        ("testground-sync-service".to_owned(), 5051)
+    );
+
+    vars.insert("TEST_INSTANCE_COUNT", "two");
+    let error = from_vars(&vars).unwrap_err();
+    assert_eq!(error.to_string(), "TEST_INSTANCE_COUNT not a u64: \"two\"");
+
+    vars.insert("TEST_INSTANCE_COUNT", "2");
+    vars.insert("TEST_SUBNET", "");
+    assert!(matches!(
+        from_vars(&vars),
+        Err(ParamsError::InvalidSubnet { .. })
+    ));
+
+    vars.remove("TEST_INSTANCE_COUNT");
+    assert_eq!(
+        from_vars(&vars).unwrap_err(),
+        ParamsError::Missing("TEST_INSTANCE_COUNT")
+    );
+}
+
+#[test]
+fn test_serde() {
+    let mut params = test_run_parameters();
+    params.test_instance_params = parse_key_val("num=2").unwrap();
+    params.test_run_timeout = Some(Duration::from_secs(600));
+    params.test_capture_profiles = "mem,cpu=30s".to_owned();
+
+    let encoded = serde_json::to_value(&params).unwrap();
+    assert_eq!(encoded["plan"], "sdk-rust");
+    assert_eq!(encoded["instances"], 1);
+    assert_eq!(encoded["params"], serde_json::json!({ "num": "2" }));
+    assert_eq!(encoded["network"], "16.0.0.0/16");
+    assert_eq!(encoded["start_time"], "2022-01-12T15:48:07-05:00");
+    assert_eq!(encoded["run_timeout"], 600_000_000_000u64);
+    assert_eq!(
+        encoded["capture_profiles"],
+        serde_json::json!({ "cpu": "30s", "mem": "" })
+    );
+    assert!(encoded.get("repo").is_none());
+
+    let decoded: RunParameters = serde_json::from_value(encoded).unwrap();
+    assert_eq!(decoded.test_instance_params, params.test_instance_params);
+    assert_eq!(decoded.test_subnet, params.test_subnet);
+    assert_eq!(decoded.test_start_time, params.test_start_time);
+    assert_eq!(decoded.test_run_timeout, params.test_run_timeout);
+    assert_eq!(decoded.test_capture_profiles, "cpu=30s,mem");
+
+    // The run environment of the Go SDK omits empty fields.
+    let decoded: RunParameters = serde_json::from_value(serde_json::json!({
+        "plan": "sdk-go",
+        "case": "example",
+        "run": "c7fjstge5te621cen4i0",
+        "instances": 2,
+        "group": "single",
+        "group_instances": 2,
+        "network": "16.0.0.0/16",
+        "start_time": "2022-01-12T20:48:07.123456789Z",
+    }))
+    .unwrap();
+    assert_eq!(decoded.test_instance_count, 2);
+    assert!(!decoded.test_sidecar);
+}
+
+#[test]
+fn test_output_path() {
+    let mut params = test_run_parameters();
+    assert_eq!(
+        params.output_path("results.csv").unwrap_err().kind(),
+        std::io::ErrorKind::NotFound
+    );
+
+    params.test_outputs_path =
+        std::env::temp_dir().join(format!("testground-output-path-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&params.test_outputs_path);
+
+    let path = params.output_path("results.csv").unwrap();
+    assert_eq!(path, params.test_outputs_path.join("results.csv"));
+    assert!(params.test_outputs_path.is_dir());
+
+    use std::io::Write;
+    params
+        .output_file("results.csv")
+        .unwrap()
+        .write_all(b"ok")
+        .unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"ok");
+
+    for name in ["", ".", "..", "../escape", "nested/file"] {
+        assert_eq!(
+            params.output_path(name).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+    }
+
+    std::fs::remove_dir_all(params.test_outputs_path).unwrap();
+}
@@ -8,8 +8,9 @@ pub struct Request {
 
     pub is_cancel: bool,
 
+    /// The request, or `None` when cancelling the request with the same ID.
     #[serde(flatten)]
-    pub request: RequestType,
+    pub request: Option<RequestType>,
 }
 
 #[derive(Serialize, Debug)]
@@ -32,12 +33,20 @@ pub enum RequestType {
     Publish { topic: String, payload: PayloadType },
     #[serde(rename = "subscribe")]
     Subscribe { topic: String },
+    #[cfg(any(test, feature = "testing"))]
+    #[serde(rename = "state_count")]
+    StateCount { state: String },
+    /// A request not modelled by the SDK, i.e. a JSON object, see
+    /// [`Client::raw_request`](crate::client::Client::raw_request).
+    #[serde(untagged)]
+    Raw(serde_json::Value),
 }
 
 #[cfg(test)]
 mod tests {
 
     use std::net::Ipv4Addr;
+    use std::time::Duration;
 
     use ipnetwork::Ipv4Network;
 
@@ -53,8 +62,8 @@ mod tests {
             ipv6: None,
             enable: true,
             default: LinkShape {
-                latency: 10000000,
-                jitter: 0,
+                latency: Duration::from_millis(10),
+                jitter: Duration::ZERO,
                 bandwidth: 1048576,
                 filter: FilterAction::Accept,
                 loss: 0.0,
@@ -81,15 +90,29 @@ mod tests {
         let req = Request {
             id: "0".to_owned(),
             is_cancel: false,
-            request: RequestType::Publish {
+            request: Some(RequestType::Publish {
                 topic: "run:abcd1234:plan:live_streming:case:quickstart:topics:network:hostname"
                     .to_owned(),
                 payload: PayloadType::Event(event),
-            },
+            }),
         };
 
         let json_req = serde_json::to_string_pretty(&req).unwrap();
 
         println!("{}", json_req);
     }
+
+    #[test]
+    fn serde_test_cancel() {
+        let req = Request {
+            id: "3".to_owned(),
+            is_cancel: true,
+            request: None,
+        };
+
+        assert_eq!(
+            serde_json::to_string(&req).unwrap(),
+            r#"{"id":"3","is_cancel":true}"#
+        );
+    }
 }
@@ -1,18 +1,26 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use serde_with::{serde_as, NoneAsEmptyString};
 
-#[derive(Deserialize, Debug)]
+use crate::errors::{Error, SyncServiceError};
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct SignalEntry {
     pub seq: u64,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Publish {
     pub seq: u64,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StateCount {
+    pub count: u64,
+}
+
 #[serde_as]
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct RawResponse {
     pub id: String,
 
@@ -25,15 +33,33 @@ pub struct RawResponse {
     pub signal_entry: Option<SignalEntry>,
 
     pub publish: Option<Publish>,
+
+    pub state_count: Option<StateCount>,
+
+    /// Fields unknown to this version of the SDK, e.g. of newer response
+    /// types.
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, Value>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ResponseType {
-    SignalEntry { seq: u64 },
-    Publish { seq: u64 },
+    SignalEntry {
+        seq: u64,
+    },
+    Publish {
+        seq: u64,
+    },
+    StateCount {
+        count: u64,
+    },
     Subscribe(serde_json::Value),
-    Error(String),
+    Error(SyncServiceError),
     Barrier,
+    /// A response of a type unknown to this version of the SDK, holding its
+    /// fields other than the `id`.
+    Unknown(serde_json::Value),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -42,45 +68,101 @@ pub struct Response {
     pub response: ResponseType,
 }
 
-impl From<RawResponse> for Response {
-    fn from(raw_response: RawResponse) -> Self {
+/// An [`Error::MalformedResponse`] holding `raw_response` as JSON.
+fn malformed(raw_response: RawResponse) -> Error {
+    Error::MalformedResponse(serde_json::to_value(raw_response).expect("Response Serialization"))
+}
+
+impl TryFrom<RawResponse> for Response {
+    type Error = Error;
+
+    fn try_from(raw_response: RawResponse) -> Result<Self, Self::Error> {
         let RawResponse {
             id,
             error,
             subscribe,
             signal_entry,
             publish,
+            state_count,
+            other,
         } = raw_response;
 
-        let response = match (error, subscribe, signal_entry, publish) {
-            (None, None, None, None) => ResponseType::Barrier,
-            (Some(error), None, None, None) => {
-                // Hack to remove extra escape characters
-                let error = serde_json::from_str(&error).expect("JSON Deserialization");
-                ResponseType::Error(error)
+        let other: serde_json::Map<_, _> = other
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .collect();
+
+        let response = match (error, subscribe, signal_entry, publish, state_count) {
+            (None, None, None, None, None) if !other.is_empty() => {
+                ResponseType::Unknown(Value::Object(other))
             }
-            (None, Some(msg), None, None) => {
+            (None, None, None, None, None) => ResponseType::Barrier,
+            (Some(error), None, None, None, None) => ResponseType::Error(parse_error(&error)),
+            (None, Some(msg), None, None, None) => {
                 // The Subscribe payload is a json encoded string, so we need to deserialize it.
-                let payload = serde_json::from_str(&msg).expect("JSON Deserialization");
-                ResponseType::Subscribe(payload)
-            }
-            (None, None, Some(signal), None) => ResponseType::SignalEntry { seq: signal.seq },
-            (None, None, None, Some(publish)) => ResponseType::Publish { seq: publish.seq },
-            (error, subscribe, signal_entry, publish) => {
-                panic!(
-                    "Incompatible Raw Response {:?}",
-                    RawResponse {
-                        id,
-                        error,
-                        subscribe,
-                        signal_entry,
-                        publish,
+                match serde_json::from_str(&msg) {
+                    Ok(payload) => ResponseType::Subscribe(payload),
+                    Err(_) => {
+                        return Err(malformed(RawResponse {
+                            id,
+                            error: None,
+                            subscribe: Some(msg),
+                            signal_entry: None,
+                            publish: None,
+                            state_count: None,
+                            other,
+                        }))
                     }
-                );
+                }
+            }
+            (None, None, Some(signal), None, None) => ResponseType::SignalEntry { seq: signal.seq },
+            (None, None, None, Some(publish), None) => ResponseType::Publish { seq: publish.seq },
+            (None, None, None, None, Some(state_count)) => ResponseType::StateCount {
+                count: state_count.count,
+            },
+            (error, subscribe, signal_entry, publish, state_count) => {
+                return Err(malformed(RawResponse {
+                    id,
+                    error,
+                    subscribe,
+                    signal_entry,
+                    publish,
+                    state_count,
+                    other,
+                }));
             }
         };
 
-        Self { id, response }
+        Ok(Self { id, response })
+    }
+}
+
+/// Parses the error payload of a response, which the sync service JSON encodes
+/// once more.
+fn parse_error(error: &str) -> SyncServiceError {
+    let error = match serde_json::from_str(error) {
+        // The encoded error may itself be a structured error.
+        Ok(Value::String(error)) => serde_json::from_str(&error).unwrap_or(Value::String(error)),
+        Ok(error) => error,
+        Err(_) => Value::String(error.to_owned()),
+    };
+
+    match error {
+        Value::Object(mut error) if error.contains_key("code") => SyncServiceError {
+            code: error.remove("code").map(into_string),
+            message: error.remove("message").map(into_string).unwrap_or_default(),
+        },
+        error => SyncServiceError {
+            code: None,
+            message: into_string(error),
+        },
+    }
+}
+
+fn into_string(value: Value) -> String {
+    match value {
+        Value::String(value) => value,
+        value => value.to_string(),
     }
 }
 
@@ -95,7 +177,7 @@ mod tests {
 
         let response: RawResponse = serde_json::from_str(raw_response).unwrap();
 
-        let response: Response = response.into();
+        let response = Response::try_from(response).unwrap();
 
         assert_eq!(
             Response {
@@ -111,7 +193,7 @@ mod tests {
 
         let response: RawResponse = serde_json::from_str(raw_response).unwrap();
 
-        let response: Response = response.into();
+        let response = Response::try_from(response).unwrap();
 
         assert_eq!(
             Response {
@@ -124,4 +206,78 @@ mod tests {
             response
         );
     }
+
+    #[test]
+    fn serde_test_structured_error() {
+        let raw_response = r#"{"id":"2","error":"\"{\\\"code\\\":\\\"rate_limited\\\",\\\"message\\\":\\\"too many requests\\\"}\"","subscribe":""}"#;
+
+        let response =
+            Response::try_from(serde_json::from_str::<RawResponse>(raw_response).unwrap()).unwrap();
+
+        assert_eq!(
+            response.response,
+            ResponseType::Error(SyncServiceError {
+                code: Some("rate_limited".to_owned()),
+                message: "too many requests".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn serde_test_unstructured_error() {
+        let raw_response = r#"{"id":"3","error":"\"context canceled\"","subscribe":""}"#;
+
+        let response =
+            Response::try_from(serde_json::from_str::<RawResponse>(raw_response).unwrap()).unwrap();
+
+        assert_eq!(
+            response.response,
+            ResponseType::Error(SyncServiceError {
+                code: None,
+                message: "context canceled".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn serde_test_unknown() {
+        let raw_response = r#"{"id":"6","error":"","subscribe":"","lock":{"holder":2},"ack":null}"#;
+
+        let response =
+            Response::try_from(serde_json::from_str::<RawResponse>(raw_response).unwrap()).unwrap();
+
+        assert_eq!(
+            response.response,
+            ResponseType::Unknown(serde_json::json!({"lock": {"holder": 2}}))
+        );
+
+        // Unknown fields without a value don't make for an unknown response.
+        let raw_response = r#"{"id":"7","error":"","subscribe":"","ack":null}"#;
+
+        let response =
+            Response::try_from(serde_json::from_str::<RawResponse>(raw_response).unwrap()).unwrap();
+
+        assert_eq!(response.response, ResponseType::Barrier);
+    }
+
+    #[test]
+    fn serde_test_malformed() {
+        let raw_response =
+            r#"{"id":"4","error":"","subscribe":"","publish":{"seq":1},"signal_entry":{"seq":1}}"#;
+
+        let response =
+            Response::try_from(serde_json::from_str::<RawResponse>(raw_response).unwrap());
+
+        assert!(matches!(
+            response,
+            Err(Error::MalformedResponse(ref raw)) if raw["id"] == "4"
+        ));
+
+        let raw_response = r#"{"id":"5","error":"","subscribe":"not json"}"#;
+
+        let response =
+            Response::try_from(serde_json::from_str::<RawResponse>(raw_response).unwrap());
+
+        assert!(matches!(response, Err(Error::MalformedResponse(_))));
+    }
 }
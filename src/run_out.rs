@@ -0,0 +1,113 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Size-based rotation of `run.out`.
+#[derive(Debug, Clone, Copy)]
+pub struct Rotation {
+    /// The size in bytes after which `run.out` is rotated.
+    pub max_size: u64,
+    /// The number of rotated files to keep, i.e. `run.out.1` to
+    /// `run.out.<max_files>`, dropping the oldest beyond that.
+    pub max_files: usize,
+}
+
+/// Appends lines to `run.out` within the outputs directory.
+#[derive(Debug)]
+pub struct RunOut {
+    path: PathBuf,
+    rotation: Option<Rotation>,
+    /// Serializes writes, and thus rotations, of all clones of a client.
+    lock: Mutex<()>,
+}
+
+impl RunOut {
+    pub fn new(path: PathBuf, rotation: Option<Rotation>) -> Self {
+        Self {
+            path,
+            rotation,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Appends `line`, rotating `run.out` first if the line would exceed the
+    /// configured maximum size.
+    pub fn write_line(&self, line: &str) -> io::Result<()> {
+        let _guard = self.lock.lock().unwrap();
+
+        if let Some(rotation) = self.rotation {
+            let size = match std::fs::metadata(&self.path) {
+                Ok(metadata) => metadata.len(),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+                Err(e) => return Err(e),
+            };
+
+            // A single line exceeding the maximum size still ends up in a file
+            // of its own.
+            if size > 0 && size + line.len() as u64 + 1 > rotation.max_size {
+                self.rotate(rotation.max_files)?;
+            }
+        }
+
+        let mut file = File::options().create(true).append(true).open(&self.path)?;
+
+        writeln!(file, "{}", line)
+    }
+
+    /// Shifts `run.out.<n>` to `run.out.<n + 1>` and `run.out` to `run.out.1`.
+    fn rotate(&self, max_files: usize) -> io::Result<()> {
+        if max_files == 0 {
+            return std::fs::remove_file(&self.path);
+        }
+
+        for n in (1..max_files).rev() {
+            match std::fs::rename(self.rotated(n), self.rotated(n + 1)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+
+        std::fs::rename(&self.path, self.rotated(1))
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        path.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation() {
+        let dir = std::env::temp_dir().join(format!("testground-rotation-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let run_out = RunOut::new(
+            dir.join("run.out"),
+            Some(Rotation {
+                max_size: 100,
+                max_files: 2,
+            }),
+        );
+
+        // 40 bytes per line including the newline, thus two lines per file.
+        let line = "a".repeat(39);
+        for _ in 0..7 {
+            run_out.write_line(&line).unwrap();
+        }
+
+        let size = |name: &str| std::fs::metadata(dir.join(name)).map(|m| m.len()).ok();
+
+        assert_eq!(size("run.out"), Some(40));
+        assert_eq!(size("run.out.1"), Some(80));
+        assert_eq!(size("run.out.2"), Some(80));
+        assert_eq!(size("run.out.3"), None);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+//! Adapters for the streams returned by the subscribe methods of
+//! [`Client`](crate::client::Client).
+
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use tokio::sync::oneshot;
+
+use crate::errors::Error;
+
+/// A handle to a subscription, see
+/// [`Client::subscribe_cancellable`](crate::client::Client::subscribe_cancellable).
+///
+/// Dropping the handle leaves the subscription running.
+#[derive(Debug)]
+pub struct Subscription {
+    cancel: oneshot::Sender<()>,
+}
+
+impl Subscription {
+    pub(crate) fn new(cancel: oneshot::Sender<()>) -> Self {
+        Self { cancel }
+    }
+
+    /// Cancels the subscription with the sync service, ending its stream once
+    /// the elements already received are consumed.
+    pub fn cancel(self) {
+        // The background task may already be gone, along with the subscription.
+        let _ = self.cancel.send(());
+    }
+}
+
+/// Validates that the sequence numbers of the items of `stream` are
+/// contiguous, starting at 1.
+///
+/// An item following a gap is replaced by [`Error::SequenceGap`], after which
+/// validation resumes from that item's sequence number, so that each gap is
+/// reported exactly once. Errors of the underlying stream are passed through.
+pub fn contiguous<S, T>(stream: S) -> impl Stream<Item = Result<(u64, T), Error>>
+where
+    S: Stream<Item = Result<(u64, T), Error>>,
+{
+    let mut expected = 1;
+
+    stream.map(move |item| {
+        let (seq, item) = item?;
+
+        if seq != expected {
+            let err = Error::SequenceGap { expected, got: seq };
+            expected = seq + 1;
+            return Err(err);
+        }
+
+        expected += 1;
+
+        Ok((seq, item))
+    })
+}
+
+/// Yields [`Error::IdleTimeout`] whenever no item of `stream` arrived within
+/// `idle` of the previous one, e.g. to detect a stalled topic.
+///
+/// The stream is not closed on a timeout, leaving it to the consumer whether
+/// to keep waiting, in which case another timeout is yielded after a further
+/// `idle` without an item.
+pub fn idle_timeout<S, T>(stream: S, idle: Duration) -> impl Stream<Item = Result<T, Error>>
+where
+    S: Stream<Item = Result<T, Error>> + Unpin,
+{
+    futures::stream::unfold(stream, move |mut stream| async move {
+        match tokio::time::timeout(idle, stream.next()).await {
+            Ok(Some(item)) => Some((item, stream)),
+            Ok(None) => None,
+            Err(_) => Some((Err(Error::IdleTimeout(idle)), stream)),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn contiguous_reports_gap() {
+        let items = futures::stream::iter([1, 2, 4, 5].map(|seq| Ok((seq, ()))));
+
+        let items: Vec<_> = contiguous(items).collect().await;
+
+        assert!(matches!(items[0], Ok((1, ()))));
+        assert!(matches!(items[1], Ok((2, ()))));
+        assert!(matches!(
+            items[2],
+            Err(Error::SequenceGap {
+                expected: 3,
+                got: 4
+            })
+        ));
+        assert!(matches!(items[3], Ok((5, ()))));
+    }
+}
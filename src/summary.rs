@@ -0,0 +1,48 @@
+use std::sync::{atomic::AtomicU64, Mutex};
+use std::time::Instant;
+
+use serde::Serialize;
+
+/// Statistics of a test instance, shared between all clones of a
+/// [`Client`](crate::client::Client).
+#[derive(Debug)]
+pub struct Stats {
+    pub start: Instant,
+    pub messages: AtomicU64,
+    pub metrics: AtomicU64,
+    pub summary: Mutex<Option<serde_json::Value>>,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            messages: Default::default(),
+            metrics: Default::default(),
+            summary: Default::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutcomeKind {
+    Success,
+    Failure,
+    Crash,
+}
+
+/// The content of `summary.json`.
+#[derive(Serialize, Debug)]
+pub struct RunSummary<'a> {
+    pub outcome: OutcomeKind,
+    pub group: &'a str,
+    pub global_seq: u64,
+    pub group_seq: u64,
+    pub duration_ms: u128,
+    pub messages: u64,
+    pub metrics: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'a str>,
+    pub summary: Option<serde_json::Value>,
+}
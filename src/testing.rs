@@ -0,0 +1,156 @@
+//! A sync service speaking the websocket protocol of the Testground sync
+//! service over TCP, allowing to exercise a [`Client`](crate::client::Client)
+//! without a running Testground daemon.
+//!
+//! Requires the `testing` feature.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use testground::testing::MockSyncService;
+//!
+//! let sync_service = MockSyncService::start().await;
+//! let client = sync_service.client_builder().build().await?;
+//!
+//! client.signal_and_wait("ready", 1).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::net::SocketAddr;
+
+use tokio::net::TcpListener;
+
+use crate::client::ClientBuilder;
+use crate::local::LocalSyncService;
+use crate::RunParameters;
+
+/// Returns the runtime parameters of a single instance run on the
+/// `local:exec` runner, i.e. without a sidecar, to adjust before building a
+/// client with [`MockSyncService::client_builder`], e.g. the
+/// `test_instance_count`.
+pub fn run_parameters() -> RunParameters {
+    crate::params::test_run_parameters()
+}
+
+/// An in-process sync service listening on a local port.
+///
+/// Every connection shares the same states and topics, thus multiple clients
+/// connected to the same service behave like instances of a single run.
+pub struct MockSyncService {
+    addr: SocketAddr,
+    service: LocalSyncService,
+}
+
+impl MockSyncService {
+    /// Starts the service on a random local port.
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .expect("Mock Sync Service Bind");
+        let addr = listener.local_addr().expect("Mock Sync Service Address");
+        let service = LocalSyncService::new();
+
+        let shared = service.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                shared.serve(socket);
+            }
+        });
+
+        Self { addr, service }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns a builder of a [`Client`](crate::client::Client) connecting
+    /// to this service, using the runtime parameters of [`run_parameters`].
+    pub fn client_builder(&self) -> ClientBuilder {
+        ClientBuilder::new()
+            .run_parameters(run_parameters())
+            .sync_service_addr(self.addr.ip().to_string(), self.addr.port())
+    }
+
+    /// Closes all open connections, as if the service went away.
+    pub fn disconnect(&self) {
+        self.service.disconnect.notify_waiters();
+    }
+
+    /// Returns the names of all topics published to.
+    pub fn topics(&self) -> Vec<String> {
+        self.service
+            .state
+            .lock()
+            .unwrap()
+            .topics
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the number of open connections.
+    pub fn connections(&self) -> usize {
+        self.service.state.lock().unwrap().connections
+    }
+
+    /// Returns the number of active subscriptions.
+    pub fn subscriptions(&self) -> usize {
+        self.service.state.lock().unwrap().subscriptions.len()
+    }
+
+    /// Returns the number of barriers waiting for their target.
+    pub fn pending_barriers(&self) -> usize {
+        self.service.state.lock().unwrap().barriers.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use futures::StreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn end_to_end() {
+        let sync_service = MockSyncService::start().await;
+
+        let mut params = run_parameters();
+        params.test_instance_count = 2;
+        params.test_group_instance_count = 2;
+
+        // Clients wait for all instances to initialize.
+        let (a, b) = tokio::join!(
+            sync_service
+                .client_builder()
+                .run_parameters(params.clone())
+                .build(),
+            sync_service.client_builder().run_parameters(params).build()
+        );
+        let (a, b) = (a.unwrap(), b.unwrap());
+
+        let (seq_a, seq_b) =
+            tokio::join!(a.signal_and_wait("ready", 2), b.signal_and_wait("ready", 2));
+        let mut seqs = vec![seq_a.unwrap(), seq_b.unwrap()];
+        seqs.sort_unstable();
+        assert_eq!(seqs, [1, 2]);
+
+        let message = serde_json::json!({"addr": "16.0.0.1"});
+        a.publish("peers", Cow::Owned(message.clone()))
+            .await
+            .unwrap();
+
+        let mut stream = b.subscribe("peers", 16).await;
+        assert_eq!(stream.next().await.unwrap().unwrap(), message);
+
+        a.barrier("ready", 2).await.unwrap();
+
+        assert!(sync_service
+            .topics()
+            .iter()
+            .any(|topic| topic.ends_with(":peers")));
+        assert_eq!(sync_service.pending_barriers(), 0);
+    }
+}